@@ -12,9 +12,9 @@ fn test_function() -> f32 {
 fn bench_engage_1k(c: &mut Criterion) {
     c.bench_function("engage_1k", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(1);
+            let machine = Machine::powered(1);
             for _ in 0..1000 {
-                machine.insert_cog(move || test_function());
+                machine.insert_cog(test_function);
             }
             machine.wait_until_done();
         });
@@ -24,9 +24,9 @@ fn bench_engage_1k(c: &mut Criterion) {
 fn bench_engage_10k(c: &mut Criterion) {
     c.bench_function("engage_10k", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(1);
+            let machine = Machine::powered(1);
             for _ in 0..10_000 {
-                machine.insert_cog(move || test_function());
+                machine.insert_cog(test_function);
             }
             machine.wait_until_done();
         });
@@ -36,9 +36,9 @@ fn bench_engage_10k(c: &mut Criterion) {
 fn bench_engage_100k(c: &mut Criterion) {
     c.bench_function("engage_100k", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(1);
+            let machine = Machine::powered(1);
             for _ in 0..100_000 {
-                machine.insert_cog(move || test_function());
+                machine.insert_cog(test_function);
             }
             machine.wait_until_done();
         });
@@ -48,11 +48,11 @@ fn bench_engage_100k(c: &mut Criterion) {
 fn bench_engage_100k_8_engines(c: &mut Criterion) {
     c.bench_function("engage_100k_8_engines", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(8);
+            let machine = Machine::powered(8);
             for _ in 0..100 {
                 let mut cogs = Vec::new();
                 for _ in 0..(100_000 / 100) {
-                    cogs.push(move || test_function());
+                    cogs.push(test_function);
                 }
                 machine.insert_cog_batch(cogs);
             }
@@ -64,11 +64,11 @@ fn bench_engage_100k_8_engines(c: &mut Criterion) {
 fn bench_engage_1m(c: &mut Criterion) {
     c.bench_function("engage_1m", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(1);
+            let machine = Machine::powered(1);
             for _ in 0..100 {
                 let mut cogs = Vec::new();
                 for _ in 0..(1_000_000 / 100) {
-                    cogs.push(move || test_function());
+                    cogs.push(test_function);
                 }
                 machine.insert_cog_batch(cogs);
             }
@@ -80,11 +80,11 @@ fn bench_engage_1m(c: &mut Criterion) {
 fn bench_engage_10m(c: &mut Criterion) {
     c.bench_function("engage_10m", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(1);
+            let machine = Machine::powered(1);
             for _ in 0..100 {
                 let mut cogs = Vec::new();
                 for _ in 0..(10_000_000 / 100) {
-                    cogs.push(move || test_function());
+                    cogs.push(test_function);
                 }
                 machine.insert_cog_batch(cogs);
             }
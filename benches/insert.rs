@@ -4,7 +4,7 @@ use rustycog::Machine;
 fn bench_insert_1k(c: &mut Criterion) {
     c.bench_function("insert_1k", |b| {
         b.iter(|| {
-            let mut machine = Machine::cold(1);
+            let machine = Machine::cold(1);
             for i in 0..1_000 {
                 machine.insert_cog(move || i);
             }
@@ -15,7 +15,7 @@ fn bench_insert_1k(c: &mut Criterion) {
 fn bench_insert_1k_as_batch(c: &mut Criterion) {
     c.bench_function("insert_1k_as_batch", |b| {
         b.iter(|| {
-            let mut machine = Machine::cold(1);
+            let machine = Machine::cold(1);
             let mut cogs = Vec::new();
             for i in 0..1_000 {
                 cogs.push(move || i);
@@ -28,7 +28,7 @@ fn bench_insert_1k_as_batch(c: &mut Criterion) {
 fn bench_insert_10k(c: &mut Criterion) {
     c.bench_function("insert_10k", |b| {
         b.iter(|| {
-            let mut machine = Machine::cold(1);
+            let machine = Machine::cold(1);
             for i in 0..10_000 {
                 machine.insert_cog(move || i);
             }
@@ -39,7 +39,7 @@ fn bench_insert_10k(c: &mut Criterion) {
 fn bench_insert_100k(c: &mut Criterion) {
     c.bench_function("insert_100k", |b| {
         b.iter(|| {
-            let mut machine = Machine::cold(1);
+            let machine = Machine::cold(1);
             for i in 0..100_000 {
                 machine.insert_cog(move || i);
             }
@@ -50,7 +50,7 @@ fn bench_insert_100k(c: &mut Criterion) {
 fn bench_insert_1m(c: &mut Criterion) {
     c.bench_function("insert_1m", |b| {
         b.iter(|| {
-            let mut machine = Machine::cold(1);
+            let machine = Machine::cold(1);
             for i in 0..10 {
                 let mut cogs = Vec::new();
                 for j in 0..100_000 {
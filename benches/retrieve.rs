@@ -12,9 +12,9 @@ fn test_function() -> f32 {
 fn bench_retrieve_1k(c: &mut Criterion) {
     c.bench_function("retrieve_1k", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(1);
+            let machine = Machine::powered(1);
             for _ in 0..1000 {
-                machine.insert_cog(move || test_function());
+                machine.insert_cog(test_function);
             }
             for i in 0..1000 {
                 machine.wait_for_result(i as CogId).unwrap();
@@ -26,9 +26,9 @@ fn bench_retrieve_1k(c: &mut Criterion) {
 fn bench_retrieve_10k(c: &mut Criterion) {
     c.bench_function("retrieve_10k", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(1);
+            let machine = Machine::powered(1);
             for _ in 0..10_000 {
-                machine.insert_cog(move || test_function());
+                machine.insert_cog(test_function);
             }
             for i in 0..10_000 {
                 let _ = machine.wait_for_result(i as CogId);
@@ -40,9 +40,9 @@ fn bench_retrieve_10k(c: &mut Criterion) {
 fn bench_retrieve_10k_8_engines(c: &mut Criterion) {
     c.bench_function("retrieve_10k_8_engies", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(8);
+            let machine = Machine::powered(8);
             for _ in 0..10_000 {
-                machine.insert_cog(move || test_function());
+                machine.insert_cog(test_function);
             }
             for i in 0..10_000 {
                 let _ = machine.wait_for_result(i as CogId);
@@ -51,12 +51,24 @@ fn bench_retrieve_10k_8_engines(c: &mut Criterion) {
     });
 }
 
+fn bench_retrieve_10k_wait_all(c: &mut Criterion) {
+    c.bench_function("retrieve_10k_wait_all", |b| {
+        b.iter(|| {
+            let machine = Machine::powered(1);
+            let ids: Vec<_> = (0..10_000)
+                .map(|_| machine.insert_cog(test_function))
+                .collect();
+            let _ = machine.wait_all(&ids);
+        });
+    });
+}
+
 fn bench_retrieve_100k(c: &mut Criterion) {
     c.bench_function("retrieve_100k", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(1);
+            let machine = Machine::powered(1);
             for _ in 0..100_000 {
-                machine.insert_cog(move || test_function());
+                machine.insert_cog(test_function);
             }
             for i in 0..100_000 {
                 let _ = machine.wait_for_result(i as CogId);
@@ -68,9 +80,9 @@ fn bench_retrieve_100k(c: &mut Criterion) {
 fn bench_retrieve_100k_8_engines(c: &mut Criterion) {
     c.bench_function("retrieve_100k_8_engies", |b| {
         b.iter(|| {
-            let mut machine = Machine::powered(8);
+            let machine = Machine::powered(8);
             for _ in 0..100_000 {
-                machine.insert_cog(move || test_function());
+                machine.insert_cog(test_function);
             }
             for i in 0..100_000 {
                 let _ = machine.wait_for_result(i as CogId);
@@ -84,6 +96,7 @@ criterion_group!(
     bench_retrieve_1k,
     bench_retrieve_10k,
     bench_retrieve_10k_8_engines,
+    bench_retrieve_10k_wait_all,
     bench_retrieve_100k,
     bench_retrieve_100k_8_engines,
 );
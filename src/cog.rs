@@ -1,21 +1,45 @@
 use std::{
+    collections::{HashMap, HashSet},
     fmt::{Debug, Formatter, Result as FormatResult},
-    sync::{Arc, Condvar, Mutex},
+    sync::{Arc, Condvar, Mutex, MutexGuard, Weak},
+    time::Instant,
 };
 
 use crate::{
-    error::CogError,
-    types::{CogId, CogType},
+    error::{CogError, CogPanic},
+    panic,
+    token::CancelToken,
+    types::{CogId, CogType, Priority},
+    watch::{Outstanding, Watch},
 };
 
+pub type CogFn<T> = Box<dyn FnOnce() -> T + Send + std::panic::UnwindSafe + 'static>;
+pub type ArcMutexCog<T> = Arc<Mutex<Cog<T, CogFn<T>>>>;
+pub type CogMap<T> = Mutex<HashMap<CogId, ArcMutexCog<T>>>;
+
 pub enum CogState<T> {
+    Blocked,
     Waiting,
     Running,
-    Panicked,
+    Panicked(CogPanic),
+    Cancelled,
+    DeadlineExceeded,
+    DependencyFailed(CogId),
+    Shutdown,
     Removed,
     Done(T),
 }
 
+/// Everything about a cog that is decided when it is inserted, besides its function
+#[derive(Default)]
+pub struct CogOptions {
+    /// The token handed to the cog, if it was inserted with one
+    pub token: Option<CancelToken>,
+    pub priority: Priority,
+    pub deadline: Option<Instant>,
+    pub outstanding: Option<Arc<Outstanding>>,
+}
+
 pub struct Cog<T, F>
 where
     T: CogType,
@@ -24,7 +48,20 @@ where
     pub id: CogId,
     pub done: Arc<(Mutex<bool>, Condvar)>,
     pub state: CogState<T>,
+    pub priority: Priority,
+    /// The dependent the result is handed over to, instead of being retrievable
+    pub consumer: Option<Weak<Mutex<Self>>>,
     func: Option<F>,
+    token: Option<CancelToken>,
+    deadline: Option<Instant>,
+    outstanding: Option<Arc<Outstanding>>,
+    /// Whether the cog failed, kept once the result is retrieved so dependents still fail
+    failed: bool,
+
+    prerequisites: usize,
+    waiting_on: Vec<Weak<Mutex<Self>>>,
+    dependents: Vec<Arc<Mutex<Self>>>,
+    watches: Vec<Arc<Watch>>,
 }
 
 impl<T, F> Debug for Cog<T, F>
@@ -42,55 +79,436 @@ where
     T: CogType,
     F: FnOnce() -> T + std::panic::UnwindSafe,
 {
-    pub fn new(id: CogId, func: F) -> Self {
+    pub fn new(id: CogId, func: F, options: CogOptions) -> Self {
         Self {
             id,
             done: Arc::new((Mutex::new(false), Condvar::new())),
             func: Some(func),
             state: CogState::Waiting,
+            priority: options.priority,
+            consumer: None,
+            token: options.token,
+            deadline: options.deadline,
+            outstanding: options.outstanding,
+            failed: false,
+
+            prerequisites: 0,
+            waiting_on: Vec::new(),
+            dependents: Vec::new(),
+            watches: Vec::new(),
+        }
+    }
+
+    /// Creates a cog that is held back until it is released with `Cog::resolve_prerequisite`.
+    /// Every call to `Cog::add_dependent` adds another prerequisite to release.
+    pub fn blocked(id: CogId, func: F, options: CogOptions) -> Self {
+        Self {
+            state: CogState::Blocked,
+            prerequisites: 1,
+            ..Self::new(id, func, options)
         }
     }
 
     pub fn get_result(&mut self) -> Result<T, CogError> {
+        // The result belongs to the consumer, the cog only stays until it has run
+        if self.is_consumed() {
+            return Err(CogError::NotInserted(self.id));
+        }
         match self.state {
-            CogState::Done(_) | CogState::Panicked => {
+            CogState::Done(_)
+            | CogState::Panicked(_)
+            | CogState::Cancelled
+            | CogState::DeadlineExceeded
+            | CogState::DependencyFailed(_)
+            | CogState::Shutdown => {
                 // Replace needs to happen since we want to move the result from Done
                 // This way, in a Machine<T>, T does not have to implement Clone or Copy
                 match std::mem::replace(&mut self.state, CogState::Removed) {
                     CogState::Done(result) => Ok(result),
-                    CogState::Panicked => Err(CogError::Panicked(self.id)),
+                    CogState::Panicked(panic) => Err(CogError::Panicked(self.id, panic)),
+                    CogState::Cancelled => Err(CogError::Cancelled(self.id)),
+                    CogState::DeadlineExceeded => Err(CogError::DeadlineExceeded(self.id)),
+                    CogState::DependencyFailed(prerequisite) => {
+                        Err(CogError::DependencyFailed(self.id, prerequisite))
+                    }
+                    CogState::Shutdown => Err(CogError::Shutdown(self.id)),
                     _ => unreachable!(),
                 }
             }
 
             CogState::Removed => Err(CogError::Removed(self.id)),
-            CogState::Waiting | CogState::Running => Err(CogError::NotCompleted(self.id)),
+            CogState::Blocked | CogState::Waiting | CogState::Running => {
+                Err(CogError::NotCompleted(self.id))
+            }
         }
     }
 
-    pub fn run(&mut self) -> Result<(), CogError> {
-        self.state = CogState::Running;
+    /// Retrieves the result like `Cog::get_result`, for the machine or a handle.
+    /// Also returns whether the cog is done with, and has to be removed from the machine.
+    pub fn retrieve(&mut self) -> (Result<T, CogError>, bool) {
+        let result = self.get_result();
+        // A consumed cog stays until its consumer has run or given up on it
+        let finished = self.is_finished() && !self.is_consumed();
+        // The result was handed over to the cog that consumed it
+        let result = match result {
+            Err(CogError::Removed(id)) => Err(CogError::NotInserted(id)),
+            result => result,
+        };
+        (result, finished)
+    }
+
+    /// Moves the result out of a cog that has succeeded, for the dependent consuming it
+    pub fn take_result(&mut self) -> Option<T> {
+        match std::mem::replace(&mut self.state, CogState::Removed) {
+            CogState::Done(result) => Some(result),
+            state => {
+                self.state = state;
+                None
+            }
+        }
+    }
 
-        let func = std::mem::take(&mut self.func).ok_or(CogError::AlreadyRan(self.id))?;
-        let result = match std::panic::catch_unwind(func) {
-            Ok(result) => {
-                self.state = CogState::Done(result);
+    /// Whether the result is reserved for a consumer that has not finished yet.
+    /// A consumer that settles without running, e.g. because another prerequisite failed,
+    /// leaves the result to be retrieved as usual.
+    pub fn is_consumed(&self) -> bool {
+        self.consumer
+            .as_ref()
+            .and_then(Weak::upgrade)
+            .is_some_and(|consumer| !consumer.lock().unwrap().is_finished())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(
+            self.state,
+            CogState::Blocked | CogState::Waiting | CogState::Running
+        )
+    }
+
+    /// Whether the cog is held back by prerequisites that have not finished yet
+    pub fn is_blocked(&self) -> bool {
+        matches!(self.state, CogState::Blocked)
+    }
+
+    /// Whether the cog is ready to run, but has not been engaged yet
+    pub fn is_waiting(&self) -> bool {
+        matches!(self.state, CogState::Waiting)
+    }
+
+    /// Whether the cog has finished with a result that has not been retrieved yet
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, CogState::Done(_))
+    }
+
+    /// Whether the cog has finished without a result
+    pub fn is_failed(&self) -> bool {
+        matches!(
+            self.state,
+            CogState::Panicked(_)
+                | CogState::Cancelled
+                | CogState::DeadlineExceeded
+                | CogState::DependencyFailed(_)
+                | CogState::Shutdown
+        )
+    }
+
+    /// Blocks until the cog has finished, or until `deadline` has passed.
+    /// Returns true if the cog has finished.
+    pub fn wait(cog: &Mutex<Self>, deadline: Option<Instant>) -> bool {
+        let locked_cog = cog.lock().unwrap();
+        if locked_cog.is_finished() {
+            return true;
+        }
+        let (lock, cvar) = &*locked_cog.done.clone();
+        // Let the cog be run
+        drop(locked_cog);
+
+        let mut done = lock.lock().unwrap();
+        while !*done {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    done = cvar.wait_timeout(done, deadline - now).unwrap().0;
+                }
+                None => done = cvar.wait(done).unwrap(),
+            }
+        }
+        true
+    }
+
+    /// Lets `watch` know once the cog has finished, right away if it already has
+    pub fn watch(cog: &Mutex<Self>, watch: &Arc<Watch>) {
+        let mut locked_cog = cog.lock().unwrap();
+        if locked_cog.is_finished() {
+            watch.notify(locked_cog.id);
+        } else {
+            locked_cog.watches.push(watch.clone());
+        }
+    }
+
+    /// Unsubscribes `watch` from the cog, if it has not finished yet
+    pub fn unwatch(cog: &Mutex<Self>, watch: &Arc<Watch>) {
+        cog.lock()
+            .unwrap()
+            .watches
+            .retain(|subscribed| !Arc::ptr_eq(subscribed, watch));
+    }
+
+    /// Cancels a cog that has not been engaged yet
+    pub fn cancel(cog: &Mutex<Self>) -> Result<(), CogError> {
+        Self::cancel_locked(cog, cog.lock().unwrap())
+    }
+
+    /// Cancels a waiting cog, or asks a running cog to stop through its token.
+    /// Running cogs without a token have no way of stopping early, so they are left alone.
+    pub fn request_cancel(cog: &Mutex<Self>) -> Result<(), CogError> {
+        let locked_cog = cog.lock().unwrap();
+        if let CogState::Running = locked_cog.state {
+            return match &locked_cog.token {
+                Some(token) => {
+                    token.cancel();
+                    Ok(())
+                }
+                None => Err(CogError::AlreadyEngaged(locked_cog.id)),
+            };
+        }
+        Self::cancel_locked(cog, locked_cog)
+    }
+
+    fn cancel_locked(cog: &Mutex<Self>, locked_cog: MutexGuard<'_, Self>) -> Result<(), CogError> {
+        let id = locked_cog.id;
+        match locked_cog.state {
+            CogState::Blocked | CogState::Waiting => {
+                let dependents = Self::settle_unrun(cog, locked_cog, CogState::Cancelled);
+                Self::resolve_dependents(id, dependents, false);
                 Ok(())
             }
-            Err(_err) => {
-                self.state = CogState::Panicked;
-                Err(CogError::Panicked(self.id))
+            CogState::Cancelled => Ok(()),
+            // The result has been retrieved, or handed over to the cog that consumed it
+            CogState::Removed => Err(CogError::NotInserted(id)),
+            CogState::Running
+            | CogState::Done(_)
+            | CogState::Panicked(_)
+            | CogState::DeadlineExceeded
+            | CogState::DependencyFailed(_)
+            | CogState::Shutdown => Err(CogError::AlreadyEngaged(id)),
+        }
+    }
+
+    /// Fails the cog with `CogState::Shutdown` if it has not been engaged yet.
+    /// Its dependents are left alone, since the shutdown reaches them as well.
+    /// Returns the ID of the cog if it was shut down.
+    pub fn shut_down(cog: &Mutex<Self>) -> Option<CogId> {
+        let locked_cog = cog.lock().unwrap();
+        if !matches!(locked_cog.state, CogState::Blocked | CogState::Waiting) {
+            return None;
+        }
+        let id = locked_cog.id;
+        Self::settle_unrun(cog, locked_cog, CogState::Shutdown);
+        Some(id)
+    }
+
+    /// Makes `dependent` wait for `prerequisite` to finish.
+    /// Returns the ID of the prerequisite if it has already failed.
+    pub fn add_dependent(
+        prerequisite_cog: &Arc<Mutex<Self>>,
+        dependent: &Arc<Mutex<Self>>,
+    ) -> Result<(), CogId> {
+        let mut prerequisite = prerequisite_cog.lock().unwrap();
+        match prerequisite.state {
+            CogState::Blocked | CogState::Waiting | CogState::Running => {
+                let mut locked_dependent = dependent.lock().unwrap();
+                locked_dependent.prerequisites += 1;
+                locked_dependent
+                    .waiting_on
+                    .push(Arc::downgrade(prerequisite_cog));
+                drop(locked_dependent);
+                prerequisite.dependents.push(dependent.clone());
+                Ok(())
+            }
+            CogState::Done(_) => Ok(()),
+            CogState::Removed if !prerequisite.failed => Ok(()),
+            CogState::Removed
+            | CogState::Panicked(_)
+            | CogState::Cancelled
+            | CogState::DeadlineExceeded
+            | CogState::DependencyFailed(_)
+            | CogState::Shutdown => Err(prerequisite.id),
+        }
+    }
+
+    /// The cogs a blocked cog is waiting on that are ready to run,
+    /// following blocked prerequisites down to the cogs holding them back.
+    pub fn waiting_prerequisites(cog: &Mutex<Self>) -> Vec<Arc<Mutex<Self>>> {
+        let mut pending: Vec<_> = cog
+            .lock()
+            .unwrap()
+            .waiting_on
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        let mut seen = HashSet::new();
+        let mut waiting = Vec::new();
+        while let Some(prerequisite) = pending.pop() {
+            let locked_prerequisite = prerequisite.lock().unwrap();
+            if !seen.insert(locked_prerequisite.id) {
+                continue;
+            }
+            if locked_prerequisite.is_blocked() {
+                pending.extend(
+                    locked_prerequisite
+                        .waiting_on
+                        .iter()
+                        .filter_map(Weak::upgrade),
+                );
+            } else if locked_prerequisite.is_waiting() {
+                drop(locked_prerequisite);
+                waiting.push(prerequisite);
+            }
+        }
+        waiting
+    }
+
+    /// Settles one prerequisite of a blocked cog.
+    /// A failed prerequisite fails the cog and everything depending on it.
+    /// Cogs that have no prerequisites left become waiting and are pushed to `ready`.
+    pub fn resolve_prerequisite(
+        cog: &Arc<Mutex<Self>>,
+        failed: Option<CogId>,
+        ready: &mut Vec<Arc<Mutex<Self>>>,
+    ) {
+        let mut locked_cog = cog.lock().unwrap();
+        // The cog might have been cancelled or failed by another prerequisite already
+        if !matches!(locked_cog.state, CogState::Blocked) {
+            return;
+        }
+        match failed {
+            None => {
+                locked_cog.prerequisites -= 1;
+                if locked_cog.prerequisites == 0 {
+                    locked_cog.state = CogState::Waiting;
+                    ready.push(cog.clone());
+                }
+            }
+            Some(prerequisite) => {
+                let id = locked_cog.id;
+                let dependents =
+                    Self::settle_unrun(cog, locked_cog, CogState::DependencyFailed(prerequisite));
+                Self::resolve_dependents(id, dependents, false);
+            }
+        }
+    }
+
+    fn resolve_dependents(
+        id: CogId,
+        dependents: Vec<Arc<Mutex<Self>>>,
+        succeeded: bool,
+    ) -> Vec<Arc<Mutex<Self>>> {
+        let failed = (!succeeded).then_some(id);
+        let mut ready = Vec::new();
+        for dependent in dependents {
+            Self::resolve_prerequisite(&dependent, failed, &mut ready);
+        }
+        ready
+    }
+
+    /// Runs the cog without holding its lock while the function executes,
+    /// so the cog can be inspected (or cancelled) by other threads meanwhile.
+    ///
+    /// Returns the dependents that became ready to run.
+    pub fn run(cog: &Mutex<Self>) -> Result<Vec<Arc<Mutex<Self>>>, CogError> {
+        let (id, func) = {
+            let mut locked_cog = cog.lock().unwrap();
+            let id = locked_cog.id;
+            match locked_cog.state {
+                CogState::Waiting => (),
+                CogState::Cancelled => return Err(CogError::Cancelled(id)),
+                _ => return Err(CogError::AlreadyRan(id)),
+            }
+            // Stale work is never run
+            if locked_cog
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                let dependents = Self::settle_unrun(cog, locked_cog, CogState::DeadlineExceeded);
+                Self::resolve_dependents(id, dependents, false);
+                return Err(CogError::DeadlineExceeded(id));
             }
+            let func = std::mem::take(&mut locked_cog.func).ok_or(CogError::AlreadyRan(id))?;
+            locked_cog.state = CogState::Running;
+            (id, func)
         };
 
+        let (state, result) = match panic::catch(func) {
+            Ok(result) => (CogState::Done(result), Ok(())),
+            Err(panic) => (
+                CogState::Panicked(panic.clone()),
+                Err(CogError::Panicked(id, panic)),
+            ),
+        };
+
+        let mut locked_cog = cog.lock().unwrap();
+        // A cog that was asked to stop may have returned early, so its result cannot be trusted
+        let (state, result) = if locked_cog
+            .token
+            .as_ref()
+            .is_some_and(|token| token.is_cancelled())
+        {
+            (CogState::Cancelled, Err(CogError::Cancelled(id)))
+        } else {
+            (state, result)
+        };
+        let dependents = locked_cog.settle(state);
+        drop(locked_cog);
+
+        let ready = Self::resolve_dependents(id, dependents, result.is_ok());
+        result.map(|()| ready)
+    }
+
+    /// Settles a cog that never ran, given its lock.
+    /// The function is dropped with the lock released, since dropping what it captured may lock
+    /// the cog again, e.g. when it held the last handle to the machine.
+    /// It is still dropped before the cog can be seen as finished, see `Cog::settle`.
+    /// Returns the dependents, which still have to be resolved.
+    fn settle_unrun(
+        cog: &Mutex<Self>,
+        mut locked_cog: MutexGuard<'_, Self>,
+        state: CogState<T>,
+    ) -> Vec<Arc<Mutex<Self>>> {
+        let func = locked_cog.func.take();
+        // Keeps the cog from being run, cancelled or seen as finished until the function is gone
+        locked_cog.state = CogState::Running;
+        drop(locked_cog);
+        drop(func);
+        cog.lock().unwrap().settle(state)
+    }
+
+    /// Moves the cog into a final state, waking up everyone waiting for it.
+    /// Returns the dependents, which have to be resolved once the lock is released.
+    ///
+    /// # Notes
+    /// The function has to be dropped by now: once the cog is finished, nothing keeps alive
+    /// what a scoped cog borrows, so `Scope` relies on the function being gone by then.
+    fn settle(&mut self, state: CogState<T>) -> Vec<Arc<Mutex<Self>>> {
+        self.state = state;
+        self.failed = self.is_failed();
         self.notify_done();
-        result
+        for watch in std::mem::take(&mut self.watches) {
+            watch.notify(self.id);
+        }
+        if let Some(outstanding) = self.outstanding.take() {
+            outstanding.finish();
+        }
+        std::mem::take(&mut self.dependents)
     }
 
     fn notify_done(&mut self) {
         let (lock, cvar) = &*self.done;
         let mut done = lock.lock().unwrap();
         *done = true;
-        cvar.notify_one();
+        cvar.notify_all();
     }
 }
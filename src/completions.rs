@@ -0,0 +1,61 @@
+use std::{iter::FusedIterator, sync::Arc};
+
+use crate::{
+    cog::ArcMutexCog,
+    error::CogError,
+    machine::Core,
+    types::{CogId, CogType},
+    watch::Watch,
+};
+
+/// Iterator over the results of cogs in the order they finish, created by
+/// `Machine::completions`
+///
+/// Each call to `next` blocks until another cog has finished.
+pub struct Completions<'machine, T: CogType> {
+    core: &'machine Core<T>,
+    watch: Arc<Watch>,
+    cogs: Vec<ArcMutexCog<T>>,
+    remaining: usize,
+}
+
+impl<'machine, T: CogType> Completions<'machine, T> {
+    pub(crate) fn new(core: &'machine Core<T>, cogs: Vec<ArcMutexCog<T>>) -> Self {
+        Self {
+            core,
+            watch: core.watch_each(&cogs),
+            remaining: cogs.len(),
+            cogs,
+        }
+    }
+}
+
+impl<T: CogType> Iterator for Completions<'_, T> {
+    type Item = (CogId, Result<T, CogError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let id = self.core.next_finished(&self.watch, &self.cogs);
+        self.remaining -= 1;
+        Some((id, self.core.get_result(id)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T: CogType> Drop for Completions<'_, T> {
+    fn drop(&mut self) {
+        // Cogs that have not finished yet would otherwise keep the watch until they do
+        if self.remaining > 0 {
+            self.core.unwatch_each(&self.cogs, &self.watch);
+        }
+    }
+}
+
+impl<T: CogType> ExactSizeIterator for Completions<'_, T> {}
+
+impl<T: CogType> FusedIterator for Completions<'_, T> {}
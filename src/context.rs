@@ -0,0 +1,133 @@
+use std::{
+    fmt::{Debug, Formatter, Result as FormatResult},
+    sync::{Arc, Weak},
+};
+
+use crate::{
+    error::CogError,
+    handle::CogHandle,
+    machine::Core,
+    types::{CogType, MachineId},
+};
+
+/// Access to the machine from inside a running cog
+///
+/// A context is handed to cogs inserted with `Machine::insert_cog_with_context`,
+/// and to every cog spawned through it.
+///
+/// # Example
+/// ```
+/// use rustycog::Machine;
+///
+/// let machine = Machine::powered(2);
+/// let id = machine.insert_cog_with_context(|context| {
+///     let children: Vec<_> = (1..=4).map(|i| context.spawn(move |_| i)).collect();
+///     children.into_iter().map(|child| child.wait().unwrap()).sum()
+/// });
+///
+/// assert_eq!(machine.wait_for_result(id), Ok(10));
+/// ```
+pub struct CogContext<T: CogType> {
+    machine_id: MachineId,
+    core: Weak<Core<T>>,
+}
+
+impl<T: CogType> Clone for CogContext<T> {
+    fn clone(&self) -> Self {
+        Self {
+            machine_id: self.machine_id,
+            core: self.core.clone(),
+        }
+    }
+}
+
+impl<T: CogType> Debug for CogContext<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        f.debug_struct("CogContext")
+            .field("machine_id", &self.machine_id)
+            .finish()
+    }
+}
+
+impl<T: CogType> CogContext<T> {
+    pub(crate) fn new(core: &Arc<Core<T>>) -> Self {
+        Self {
+            machine_id: core.id,
+            core: Arc::downgrade(core),
+        }
+    }
+
+    /// The ID of the machine running the cog
+    pub fn machine_id(&self) -> MachineId {
+        self.machine_id
+    }
+
+    /// Spawn a child cog
+    ///
+    /// When called on an engine, the child is queued on that engine, so it is likely to run
+    /// on the same thread unless an idle engine steals it.
+    /// Waiting on the returned handle runs the child right away if no engine has
+    /// picked it up yet.
+    ///
+    /// # Panics
+    /// Panics if the machine has been dropped, which can only happen if the context was
+    /// moved out of the cog it was handed to.
+    pub fn spawn<F>(&self, func: F) -> CogHandle<T>
+    where
+        F: FnOnce(CogContext<T>) -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        let core = self.core.upgrade().expect("The machine has been dropped");
+        let context = self.clone();
+        let cog = core.insert_local_cog(Box::new(move || func(context)));
+        let id = cog.lock().unwrap().id;
+        CogHandle::new(id, cog, &core)
+    }
+
+    /// Run two closures, possibly in parallel, and return both results
+    ///
+    /// Works like `Machine::join`, with `b` queued on the engine running the cog
+    /// so an idle engine can steal it. While waiting, the engine keeps running other cogs.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - Either closure panicked (`CogError::Panicked`).
+    ///
+    /// # Panics
+    /// Panics if the machine has been dropped, see `CogContext::spawn`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{CogContext, Machine};
+    ///
+    /// fn fibonacci(context: CogContext<u64>, n: u64) -> u64 {
+    ///     if n < 2 {
+    ///         return n;
+    ///     }
+    ///     let (a, b) = context
+    ///         .join(
+    ///             move |context| fibonacci(context, n - 1),
+    ///             move |context| fibonacci(context, n - 2),
+    ///         )
+    ///         .unwrap();
+    ///     a + b
+    /// }
+    ///
+    /// // A single engine is enough, since it keeps working while it waits
+    /// let machine = Machine::powered(1);
+    /// let id = machine.insert_cog_with_context(|context| fibonacci(context, 15));
+    ///
+    /// assert_eq!(machine.wait_for_result(id), Ok(610));
+    /// ```
+    pub fn join<A, B>(&self, a: A, b: B) -> Result<(T, T), CogError>
+    where
+        A: FnOnce(CogContext<T>) -> T + Send + std::panic::UnwindSafe + 'static,
+        B: FnOnce(CogContext<T>) -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        let core = self.core.upgrade().expect("The machine has been dropped");
+        let (a_context, b_context) = (self.clone(), self.clone());
+        core.join(
+            Box::new(move || a(a_context)),
+            Box::new(move || b(b_context)),
+        )
+    }
+}
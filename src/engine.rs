@@ -1,31 +1,55 @@
 use std::{
-    collections::VecDeque,
-    sync::{Arc, Condvar, Mutex, RwLock},
+    cell::Cell,
+    sync::{
+        Arc, Condvar, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
     thread::JoinHandle,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    cog::Cog,
-    types::{CogType, EngineId},
+    cog::{ArcMutexCog, Cog},
+    queue::PriorityQueue,
+    types::{CogType, EngineId, MachineId, Priority},
 };
 
-type CogFn<T> = Box<dyn FnOnce() -> T + Send + std::panic::UnwindSafe + 'static>;
-type ArcMutexCog<T> = Arc<Mutex<Cog<T, CogFn<T>>>>;
+pub type EngineList<T> = Arc<RwLock<Vec<Arc<RwLock<Engine<T>>>>>>;
+pub type LocalQueue<T> = Arc<RwLock<PriorityQueue<ArcMutexCog<T>>>>;
+
+thread_local! {
+    /// The machine and engine running on this thread, if it is an engine thread
+    static CURRENT: Cell<Option<(MachineId, EngineId)>> = const { Cell::new(None) };
+}
+
+/// The machine and engine running on the current thread, if it is an engine thread
+pub fn current() -> Option<(MachineId, EngineId)> {
+    CURRENT.get()
+}
+
+/// When an idle engine is allowed to shut itself down
+#[derive(Debug, Clone, Copy)]
+pub struct Retirement {
+    pub min_engines: usize,
+    pub keep_alive: Duration,
+}
 
 pub struct Engine<T>
 where
     T: CogType,
 {
-    _id: EngineId,
+    pub id: EngineId,
 
-    pub local_queue: Arc<RwLock<VecDeque<ArcMutexCog<T>>>>,
+    pub local_queue: LocalQueue<T>,
 
-    engines: Arc<RwLock<Vec<Arc<RwLock<Engine<T>>>>>>,
+    engines: EngineList<T>,
 
     handle: Option<JoinHandle<()>>,
     termination_flag: Arc<RwLock<bool>>,
 
     work: Arc<(Mutex<bool>, Condvar)>,
+    paused: Arc<AtomicBool>,
+    retirement: Option<Retirement>,
 }
 
 impl<T> Engine<T>
@@ -33,14 +57,17 @@ where
     T: CogType,
 {
     pub fn new(
-        id: usize,
-        engines: Arc<RwLock<Vec<Arc<RwLock<Engine<T>>>>>>,
+        id: EngineId,
+        machine_id: MachineId,
+        engines: EngineList<T>,
         work: Arc<(Mutex<bool>, Condvar)>,
+        paused: Arc<AtomicBool>,
+        retirement: Option<Retirement>,
     ) -> Arc<RwLock<Self>> {
         let engine = Arc::new(RwLock::new(Self {
-            _id: id,
+            id,
 
-            local_queue: Arc::new(RwLock::new(VecDeque::new())),
+            local_queue: Arc::new(RwLock::new(PriorityQueue::new())),
 
             engines,
 
@@ -48,70 +75,186 @@ where
             termination_flag: Arc::new(RwLock::new(false)),
 
             work,
+            paused,
+            retirement,
         }));
-        let handle = Some(engine.read().unwrap().run(engine.clone()));
+        let handle = Some(engine.read().unwrap().run(machine_id, engine.clone()));
         engine.write().unwrap().handle = handle;
         engine
     }
 
-    fn run(&self, arc_pointer: Arc<RwLock<Self>>) -> JoinHandle<()> {
+    fn run(&self, machine_id: MachineId, arc_pointer: Arc<RwLock<Self>>) -> JoinHandle<()> {
         let local_queue = self.local_queue.clone();
         let termination_flag = self.termination_flag.clone();
         let engines = self.engines.clone();
-        // let id = self._id;
+        let id = self.id;
         let work = self.work.clone();
+        let paused = self.paused.clone();
+        let retirement = self.retirement;
 
         std::thread::spawn(move || {
+            CURRENT.set(Some((machine_id, id)));
+            // Wakeups without work for this engine do not count as work, so they do not keep
+            // an otherwise idle engine from retiring
+            let mut last_work = Instant::now();
             loop {
                 if *termination_flag.read().unwrap() {
                     return;
                 }
-                if let Some(cog) = local_queue.write().unwrap().pop_front() {
-                    let _ = cog.lock().unwrap().run();
-                } else if let Some(cogs) = Self::cog_steal(&engines, &arc_pointer) {
-                    local_queue.write().unwrap().extend(cogs);
+                // A paused machine leaves its cogs queued until it is resumed
+                let running = !paused.load(Ordering::SeqCst);
+                // Pop in its own statement, so the queue is not locked while the cog runs
+                let cog = if running {
+                    local_queue.write().unwrap().pop_front()
+                } else {
+                    None
+                };
+                if let Some(cog) = cog {
+                    if let Ok(released) = Cog::run(&cog) {
+                        Self::queue_released(&local_queue, released);
+                    }
+                    last_work = Instant::now();
+                } else if running && let Some(mut cogs) = Self::cog_steal(&engines, &arc_pointer) {
+                    local_queue.write().unwrap().append(&mut cogs);
+                    last_work = Instant::now();
                 } else {
                     let (lock, cvar) = &*work;
                     let mut ready = lock.lock().unwrap();
+                    let mut idle = false;
                     while !*ready && !*termination_flag.read().unwrap() {
-                        ready = cvar.wait(ready).unwrap();
+                        match retirement {
+                            Some(retirement) => {
+                                let idle_for = last_work.elapsed();
+                                if idle_for >= retirement.keep_alive {
+                                    idle = true;
+                                    break;
+                                }
+                                ready = cvar
+                                    .wait_timeout(ready, retirement.keep_alive - idle_for)
+                                    .unwrap()
+                                    .0;
+                            }
+                            None => ready = cvar.wait(ready).unwrap(),
+                        }
                     }
                     *ready = false;
+                    drop(ready);
+
+                    if idle && let Some(retirement) = retirement {
+                        if Self::retire(&engines, &arc_pointer, &local_queue, retirement) {
+                            return;
+                        }
+                        // Staying on, so wait another full keep-alive before trying again
+                        last_work = Instant::now();
+                    }
                 }
             }
         })
     }
 
+    /// Queues cogs whose prerequisites were just finished by this engine
+    fn queue_released(
+        local_queue: &RwLock<PriorityQueue<ArcMutexCog<T>>>,
+        released: Vec<ArcMutexCog<T>>,
+    ) {
+        if released.is_empty() {
+            return;
+        }
+        let mut local_queue = local_queue.write().unwrap();
+        for cog in released {
+            let priority = cog.lock().unwrap().priority;
+            local_queue.push_back(cog, priority);
+        }
+    }
+
+    /// Removes an idle engine from the engine list, keeping at least `min_engines` engines.
+    /// Returns true if the engine should stop running.
+    fn retire(
+        engines: &EngineList<T>,
+        self_pointer: &Arc<RwLock<Self>>,
+        local_queue: &RwLock<PriorityQueue<ArcMutexCog<T>>>,
+        retirement: Retirement,
+    ) -> bool {
+        // Holding the write lock keeps new cogs from being distributed to this engine
+        let mut engines = engines.write().unwrap();
+        let Some(position) = engines
+            .iter()
+            .position(|engine| Arc::ptr_eq(engine, self_pointer))
+        else {
+            // The machine has already taken the engine out to kill it
+            return true;
+        };
+        if engines.len() <= retirement.min_engines || !local_queue.read().unwrap().is_empty() {
+            return false;
+        }
+        engines.remove(position);
+        true
+    }
+
+    /// Steals a share of the most important work queued on any other engine
     fn cog_steal(
-        engines: &Arc<RwLock<Vec<Arc<RwLock<Engine<T>>>>>>,
+        engines: &EngineList<T>,
         self_pointer: &Arc<RwLock<Self>>,
-    ) -> Option<VecDeque<ArcMutexCog<T>>> {
-        for engine in engines.read().unwrap().iter() {
-            if Arc::ptr_eq(engine, self_pointer) {
-                continue;
-            }
-            let engine = engine.read().unwrap();
-            let mut queue = engine.local_queue.write().unwrap();
-            let len = queue.len();
-            if len > 0 {
-                return Some(
-                    queue
-                        .drain(0..usize::max(1, len / engines.read().unwrap().len()))
-                        .collect(),
-                );
-            }
+    ) -> Option<PriorityQueue<ArcMutexCog<T>>> {
+        let engines = engines.read().unwrap();
+        let (victim, priority) = Self::busiest(&engines, Some(self_pointer))?;
+
+        let victim = victim.read().unwrap();
+        let mut queue = victim.local_queue.write().unwrap();
+        // The queue might have been drained since it was inspected
+        match queue.top_priority() {
+            Some(top) if top >= priority => Some(queue.steal(top, engines.len())),
+            Some(_) | None => None,
         }
-        None
     }
 
-    pub fn kill(&mut self) {
-        *self.termination_flag.write().unwrap() = true;
-        if let Some(handle) = std::mem::take(&mut self.handle) {
-            self.notify_work_to_kill();
+    /// Takes the most important cog queued on any engine, for threads helping out while they wait
+    pub fn steal_one(engines: &EngineList<T>) -> Option<ArcMutexCog<T>> {
+        let engines = engines.read().unwrap();
+        let (victim, _) = Self::busiest(&engines, None)?;
+
+        let victim = victim.read().unwrap();
+        victim.local_queue.write().unwrap().pop_front()
+    }
+
+    /// The engine with the most important work queued, besides `exclude`
+    fn busiest<'a>(
+        engines: &'a [Arc<RwLock<Self>>],
+        exclude: Option<&Arc<RwLock<Self>>>,
+    ) -> Option<(&'a Arc<RwLock<Self>>, Priority)> {
+        engines
+            .iter()
+            .filter(|engine| !exclude.is_some_and(|exclude| Arc::ptr_eq(engine, exclude)))
+            .filter_map(|engine| {
+                let priority = engine
+                    .read()
+                    .unwrap()
+                    .local_queue
+                    .read()
+                    .unwrap()
+                    .top_priority();
+                priority.map(|priority| (engine, priority))
+            })
+            .max_by_key(|(_, priority)| *priority)
+    }
+
+    /// Stops the engine once its current cog has finished.
+    /// The engine is not locked while waiting, so the cog can still look it up.
+    pub fn kill(engine: &RwLock<Self>) {
+        if let Some(handle) = Self::stop(engine) {
             let _ = handle.join();
         }
     }
 
+    /// Tells the engine to stop once its current cog has finished, without waiting for it.
+    /// Dropping the returned handle detaches the engine's thread.
+    pub fn stop(engine: &RwLock<Self>) -> Option<JoinHandle<()>> {
+        let mut engine = engine.write().unwrap();
+        *engine.termination_flag.write().unwrap() = true;
+        engine.notify_work_to_kill();
+        std::mem::take(&mut engine.handle)
+    }
+
     fn notify_work_to_kill(&self) {
         let (lock, cvar) = &*self.work;
         let mut work = lock.lock().unwrap();
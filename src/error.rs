@@ -1,3 +1,9 @@
+use std::{
+    backtrace::Backtrace,
+    fmt::{Display, Formatter, Result as FormatResult},
+    sync::Arc,
+};
+
 use thiserror::Error;
 
 use crate::types::CogId;
@@ -14,7 +20,7 @@ pub enum CogError {
     /// ```
     /// use rustycog::{Machine, error::CogError};
     ///
-    /// let mut machine = Machine::<i32>::powered(1);
+    /// let machine = Machine::<i32>::powered(1);
     /// let non_existent_id = 999;
     ///
     /// assert_eq!(machine.get_result(non_existent_id), Err(CogError::NotInserted(999)));
@@ -39,7 +45,7 @@ pub enum CogError {
     /// ```
     /// use rustycog::{Machine, error::CogError};
     ///
-    /// let mut machine = Machine::powered(1);
+    /// let machine = Machine::powered(1);
     /// let cog_id = machine.insert_cog(|| {
     ///     std::thread::sleep(std::time::Duration::from_secs(2));
     ///     42
@@ -50,6 +56,27 @@ pub enum CogError {
     #[error("Cog {0} has not completed yet")]
     NotCompleted(CogId),
 
+    /// The Cog (task) did not complete in time.
+    ///
+    /// This error occurs when a timed wait runs out before the Cog has completed.
+    /// The Cog is left in the Machine, so its result can still be retrieved later.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::<i32>::cold(1);
+    /// let cog_id = machine.insert_cog(|| 42);
+    ///
+    /// assert_eq!(
+    ///     machine.wait_for_result_timeout(cog_id, Duration::from_millis(1)),
+    ///     Err(CogError::TimedOut(cog_id))
+    /// );
+    /// ```
+    #[error("Timed out waiting for cog {0}")]
+    TimedOut(CogId),
+
     /// The Cog (task) panicked during execution.
     ///
     /// This error occurs if the Cog encountered a panic while running.
@@ -58,13 +85,117 @@ pub enum CogError {
     /// ```
     /// use rustycog::{Machine, error::CogError};
     ///
-    /// let mut machine = Machine::powered(1);
+    /// let machine = Machine::powered(1);
     /// let cog_id = machine.insert_cog(|| panic!("Task panicked :("));
     ///
-    /// assert_eq!(machine.wait_for_result(cog_id), Err(CogError::Panicked(cog_id)));
+    /// match machine.wait_for_result(cog_id) {
+    ///     Err(CogError::Panicked(id, panic)) => {
+    ///         assert_eq!(id, cog_id);
+    ///         assert_eq!(panic.message(), Some("Task panicked :("));
+    ///         assert!(panic.location().is_some());
+    ///     }
+    ///     _ => unreachable!(),
+    /// }
+    /// ```
+    #[error("Cog {0} panicked: {1}")]
+    Panicked(CogId, CogPanic),
+
+    /// The Cog (task) was cancelled before it was run.
+    ///
+    /// This error occurs when retrieving the result of a Cog that was cancelled
+    /// with `Machine::cancel_cog`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::cold(1);
+    /// let cog_id = machine.insert_cog(|| 42);
+    /// machine.cancel_cog(cog_id).unwrap();
+    ///
+    /// assert_eq!(machine.get_result(cog_id), Err(CogError::Cancelled(cog_id)));
+    /// ```
+    #[error("Cog {0} was cancelled")]
+    Cancelled(CogId),
+
+    /// The deadline of the Cog (task) passed before it was run.
+    ///
+    /// This error occurs when a Cog inserted with `Machine::insert_cog_with_deadline` was
+    /// still waiting to be engaged when its deadline passed. The Cog is never run.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    /// use std::time::Instant;
+    ///
+    /// let machine = Machine::powered(1);
+    /// let cog_id = machine.insert_cog_with_deadline(|| 42, Instant::now());
+    ///
+    /// assert_eq!(machine.wait_for_result(cog_id), Err(CogError::DeadlineExceeded(cog_id)));
+    /// ```
+    #[error("Cog {0} was not run before its deadline")]
+    DeadlineExceeded(CogId),
+
+    /// A prerequisite of the Cog (task) failed, so the Cog was never run.
+    ///
+    /// The first ID is the Cog itself, the second ID is the prerequisite that panicked,
+    /// was cancelled, missed its deadline or failed itself.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::powered(1);
+    /// let prerequisite_id = machine.insert_cog(|| panic!("Task panicked :("));
+    /// let cog_id = machine.insert_cog_after(&[prerequisite_id], || 42).unwrap();
+    ///
+    /// assert_eq!(
+    ///     machine.wait_for_result(cog_id),
+    ///     Err(CogError::DependencyFailed(cog_id, prerequisite_id))
+    /// );
     /// ```
-    #[error("Cog {0} panicked")]
-    Panicked(CogId),
+    #[error("Cog {0} was not run since its prerequisite cog {1} failed")]
+    DependencyFailed(CogId, CogId),
+
+    /// The Machine (task manager) was shut down before the Cog (task) was run.
+    ///
+    /// This error occurs when a Machine is shut down with `ShutdownMode::Abort`, or dropped,
+    /// while the Cog was still waiting to be engaged. The Cog is never run.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::cold(1);
+    /// let handle = machine.insert_cog_with_handle(|| 42);
+    /// drop(machine);
+    ///
+    /// assert_eq!(handle.wait(), Err(CogError::Shutdown(0)));
+    /// ```
+    #[error("Cog {0} was not run since its machine was shut down")]
+    Shutdown(CogId),
+
+    /// The Cog (task) has already started running or has finished.
+    ///
+    /// This error occurs when trying to cancel a Cog that can no longer be cancelled.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::powered(1);
+    /// let (started_tx, started_rx) = std::sync::mpsc::channel();
+    /// let cog_id = machine.insert_cog(move || {
+    ///     started_tx.send(()).unwrap();
+    ///     std::thread::sleep(std::time::Duration::from_millis(100));
+    ///     42
+    /// });
+    ///
+    /// started_rx.recv().unwrap();
+    /// assert_eq!(machine.cancel_cog(cog_id), Err(CogError::AlreadyEngaged(cog_id)));
+    /// ```
+    #[error("Cog {0} has already been engaged")]
+    AlreadyEngaged(CogId),
 
     /// The Cog (task) has already run and cannot be run again.
     ///
@@ -75,6 +206,85 @@ pub enum CogError {
     AlreadyRan(CogId),
 }
 
+/// Describes the panic of a Cog (task).
+///
+/// The message is taken from the panic payload, if it is a string.
+/// The location and backtrace are recorded by a panic hook that is only active while
+/// Cogs are running; the backtrace is only captured if enabled through the
+/// `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment variables.
+///
+/// Two `CogPanic`s are equal if their messages and locations are equal.
+///
+/// # Example
+/// ```
+/// use rustycog::{Machine, error::CogError};
+///
+/// let machine = Machine::<i32>::cold(1);
+/// let caught_id = machine.insert_cog(|| std::panic::catch_unwind(|| panic!("Caught")).unwrap_or(0));
+/// let resumed_id = machine.insert_cog(|| std::panic::resume_unwind(Box::new("Resumed")));
+///
+/// assert_eq!(machine.wait_for_result(caught_id), Ok(0));
+/// match machine.wait_for_result(resumed_id) {
+///     Err(CogError::Panicked(_, panic)) => {
+///         assert_eq!(panic.message(), Some("Resumed"));
+///         // Resumed panics do not pass through the panic hook, so their location is unknown
+///         assert_eq!(panic.location(), None);
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CogPanic {
+    message: Option<String>,
+    location: Option<String>,
+    backtrace: Option<Arc<Backtrace>>,
+}
+
+impl CogPanic {
+    pub(crate) fn new(
+        message: Option<String>,
+        location: Option<String>,
+        backtrace: Option<Arc<Backtrace>>,
+    ) -> Self {
+        Self {
+            message,
+            location,
+            backtrace,
+        }
+    }
+
+    /// The panic message, if the Cog panicked with a `&str` or `String`
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    /// The source location of the panic, formatted as `file:line:column`
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// The backtrace of the panic
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_deref()
+    }
+}
+
+impl PartialEq for CogPanic {
+    fn eq(&self, other: &Self) -> bool {
+        self.message == other.message && self.location == other.location
+    }
+}
+
+impl Display for CogPanic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        write!(f, "{}", self.message().unwrap_or("Box<dyn Any>"))?;
+        if let Some(location) = self.location() {
+            write!(f, " at {location}")?;
+        }
+        Ok(())
+    }
+}
+
 /// Represents errors that can occur when interacting with a Machine (task manager).
 #[derive(Error, Debug, PartialEq)]
 pub enum MachineError {
@@ -85,4 +295,23 @@ pub enum MachineError {
     /// called.
     #[error("Machine already powered")]
     AlreadyPowered,
+
+    /// The Machine (task manager) is already paused
+    ///
+    /// This error indicates that `Machine::pause` was called on a paused machine.
+    #[error("Machine already paused")]
+    AlreadyPaused,
+
+    /// The Machine (task manager) is not paused
+    ///
+    /// This error indicates that `Machine::resume` was called on a machine that was not paused.
+    #[error("Machine not paused")]
+    NotPaused,
+
+    /// The Machine (task manager) did not finish its cogs in time
+    ///
+    /// This error indicates that `Machine::wait_until_done_timeout` gave up before every cog
+    /// had finished. The cogs keep running.
+    #[error("Timed out waiting for the machine")]
+    TimedOut,
 }
@@ -0,0 +1,281 @@
+use std::{
+    fmt::{Debug, Formatter, Result as FormatResult},
+    marker::PhantomData,
+    sync::{Arc, Weak},
+};
+
+use crate::{
+    cog::{ArcMutexCog, Cog},
+    error::CogError,
+    machine::Core,
+    types::{AnyResult, CogId, CogType, MachineId},
+};
+
+/// A handle to a single cog (task) in a machine
+///
+/// The handle can be sent to other threads, and retrieves the result of its cog
+/// without needing access to the machine.
+/// As with `Machine::get_result`, the result can only be retrieved once, either through
+/// the handle (or one of its clones) or through the machine.
+///
+/// # Example
+/// ```
+/// use rustycog::Machine;
+///
+/// let machine = Machine::powered(4);
+/// let handle = machine.insert_cog_with_handle(|| 42);
+///
+/// assert_eq!(handle.wait(), Ok(42));
+/// ```
+pub struct CogHandle<T: CogType> {
+    id: CogId,
+    machine_id: MachineId,
+    target: Arc<dyn Target<T>>,
+}
+
+impl<T: CogType> Clone for CogHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            machine_id: self.machine_id,
+            target: self.target.clone(),
+        }
+    }
+}
+
+impl<T: CogType> Debug for CogHandle<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FormatResult {
+        f.debug_struct("CogHandle")
+            .field("id", &self.id)
+            .field("machine_id", &self.machine_id)
+            .finish()
+    }
+}
+
+impl<T: CogType> CogHandle<T> {
+    pub(crate) fn new(id: CogId, cog: ArcMutexCog<T>, core: &Arc<Core<T>>) -> Self {
+        Self {
+            id,
+            machine_id: core.id,
+            target: Arc::new(Stored {
+                id,
+                cog,
+                core: Arc::downgrade(core),
+            }),
+        }
+    }
+
+    /// The ID of the cog in its machine
+    pub fn id(&self) -> CogId {
+        self.id
+    }
+
+    /// The ID of the machine the cog was inserted into
+    pub fn machine_id(&self) -> MachineId {
+        self.machine_id
+    }
+
+    /// Check whether the cog has finished, whether it succeeded or not
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    ///
+    /// let machine = Machine::powered(1);
+    /// let handle = machine.insert_cog_with_handle(|| 42);
+    ///
+    /// handle.clone().wait().unwrap();
+    /// assert!(handle.is_finished());
+    /// ```
+    pub fn is_finished(&self) -> bool {
+        self.target.is_finished()
+    }
+
+    /// Get the result of the cog without blocking
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The cog is not done yet (`CogError::NotCompleted`).
+    /// - The result has already been retrieved (`CogError::NotInserted`).
+    /// - The cog failed, see `Machine::get_result`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    /// use rustycog::error::CogError;
+    ///
+    /// let machine = Machine::cold(1);
+    /// let handle = machine.insert_cog_with_handle(|| 42);
+    ///
+    /// assert_eq!(handle.try_get(), Err(CogError::NotCompleted(handle.id())));
+    /// ```
+    pub fn try_get(&self) -> Result<T, CogError> {
+        self.target.try_get()
+    }
+
+    /// Block until the cog has finished and get its result
+    ///
+    /// If the cog is still waiting to be engaged, it is run on the calling thread,
+    /// like with `Machine::wait_for_result`.
+    /// When called from inside a cog, the engine keeps running other queued cogs until the
+    /// cog has finished, so even a single engine can run cogs that wait on each other.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The result has already been retrieved (`CogError::NotInserted`).
+    /// - The cog failed, see `Machine::get_result`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    ///
+    /// let machine = Machine::powered(4);
+    /// let handles: Vec<_> = (0..4)
+    ///     .map(|i| machine.insert_cog_with_handle(move || i * 2))
+    ///     .collect();
+    ///
+    /// let waiters: Vec<_> = handles
+    ///     .into_iter()
+    ///     .map(|handle| std::thread::spawn(move || handle.wait()))
+    ///     .collect();
+    /// for (i, waiter) in waiters.into_iter().enumerate() {
+    ///     assert_eq!(waiter.join().unwrap(), Ok(i * 2));
+    /// }
+    /// ```
+    ///
+    /// Waiting inside a cog, on a cog that is held back by work queued behind the waiter:
+    /// ```
+    /// use rustycog::{CogHandle, Machine};
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let machine = Machine::powered(1);
+    /// let (started_tx, started_rx) = channel();
+    /// let (handle_tx, handle_rx) = channel::<CogHandle<i32>>();
+    ///
+    /// let waiter_id = machine.insert_cog(move || {
+    ///     started_tx.send(()).unwrap();
+    ///     handle_rx.recv().unwrap().wait().unwrap() + 2
+    /// });
+    ///
+    /// // The only engine is busy with the waiter, so these are queued behind it
+    /// started_rx.recv().unwrap();
+    /// let input_id = machine.insert_cog(|| 20);
+    /// let double_id = machine.insert_cog_then(input_id, |input| input * 2).unwrap();
+    /// handle_tx.send(machine.handle(double_id).unwrap()).unwrap();
+    ///
+    /// assert_eq!(machine.wait_for_result(waiter_id), Ok(42));
+    /// ```
+    pub fn wait(self) -> Result<T, CogError> {
+        self.target.wait();
+        self.target.try_get()
+    }
+
+    /// Cancel the cog if it has not started running yet
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The result has already been retrieved (`CogError::NotInserted`).
+    /// - The cog has already been engaged, see `Machine::cancel_cog`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    /// use rustycog::error::CogError;
+    ///
+    /// let machine = Machine::cold(1);
+    /// let handle = machine.insert_cog_with_handle(|| 42);
+    ///
+    /// handle.cancel().unwrap();
+    /// assert_eq!(handle.clone().wait(), Err(CogError::Cancelled(0)));
+    /// assert_eq!(handle.cancel(), Err(CogError::NotInserted(0)));
+    /// ```
+    pub fn cancel(&self) -> Result<(), CogError> {
+        self.target.cancel()
+    }
+}
+
+impl CogHandle<AnyResult> {
+    /// Turns a handle to a cog of an untyped machine into a handle to the type the cog returns.
+    /// Retrieving the result panics if the cog returned another type.
+    pub(crate) fn downcast<R: CogType>(self) -> CogHandle<R> {
+        CogHandle {
+            id: self.id,
+            machine_id: self.machine_id,
+            target: Arc::new(Downcast {
+                target: self.target,
+                result: PhantomData,
+            }),
+        }
+    }
+}
+
+/// The cog behind a handle, independent of how its machine stores results
+trait Target<T>: Send + Sync {
+    fn is_finished(&self) -> bool;
+    fn try_get(&self) -> Result<T, CogError>;
+    fn wait(&self);
+    fn cancel(&self) -> Result<(), CogError>;
+}
+
+/// A cog whose result is stored as is
+struct Stored<T: CogType> {
+    id: CogId,
+    cog: ArcMutexCog<T>,
+    core: Weak<Core<T>>,
+}
+
+impl<T: CogType> Target<T> for Stored<T> {
+    fn is_finished(&self) -> bool {
+        self.cog.lock().unwrap().is_finished()
+    }
+
+    fn try_get(&self) -> Result<T, CogError> {
+        let (result, finished) = self.cog.lock().unwrap().retrieve();
+        if finished && let Some(core) = self.core.upgrade() {
+            core.cogs.lock().unwrap().remove(&self.id);
+        }
+        result
+    }
+
+    fn wait(&self) {
+        match self.core.upgrade() {
+            Some(core) => core.wait_for_cog(&self.cog),
+            // Without the machine, there is nothing to run the cog or its dependents
+            None => {
+                Cog::wait(&self.cog, None);
+            }
+        }
+    }
+
+    fn cancel(&self) -> Result<(), CogError> {
+        Cog::cancel(&self.cog)
+    }
+}
+
+/// A cog of an untyped machine, whose result is stored as `AnyResult`
+struct Downcast<R> {
+    target: Arc<dyn Target<AnyResult>>,
+    result: PhantomData<fn() -> R>,
+}
+
+impl<R: CogType> Target<R> for Downcast<R> {
+    fn is_finished(&self) -> bool {
+        self.target.is_finished()
+    }
+
+    fn try_get(&self) -> Result<R, CogError> {
+        self.target.try_get().map(|result| {
+            *result
+                .downcast::<R>()
+                .expect("The cog was inserted with this result type")
+        })
+    }
+
+    fn wait(&self) {
+        self.target.wait()
+    }
+
+    fn cancel(&self) -> Result<(), CogError> {
+        self.target.cancel()
+    }
+}
@@ -12,7 +12,7 @@
 //! ```
 //! use rustycog::Machine;
 //!
-//! let mut machine = Machine::powered(4);
+//! let machine = Machine::powered(4);
 //! let cog_id = machine.insert_cog(|| {
 //!     println!("Hello, RustyCog!");
 //!     42
@@ -23,11 +23,10 @@
 //! ```
 //!
 //! ## Dynamic Typing
-//! RustyCog can also handle dynamically typed tasks, but you (the user) are responsible
-//! for managing type safety if using the `Any` trait.
-//! This gives you flexibility without sacrificing performance.
+//! A `Machine<T>` runs cogs returning the same type. To run cogs of different types on one
+//! pool of engines, use an enum or an `AnyMachine`.
 //!
-//! ### Example 1: Using Enums (Recommended)
+//! ### Example 1: Using Enums
 //! ```
 //! use rustycog::Machine;
 //!
@@ -36,38 +35,54 @@
 //!     Bool(bool),
 //! }
 //!
-//! let mut machine = Machine::<MyTypes>::powered(4);
+//! let machine = Machine::<MyTypes>::powered(4);
 //! machine.insert_cog(|| MyTypes::Int(42));
 //! machine.insert_cog(|| MyTypes::Bool(true));
 //! ```
 //!
-//! ### Example 2: Using `Box<dyn Any>` (Advanced)
-//! NOTE: You could replace `Box` with any other smart pointer, as long as it implements Send
+//! ### Example 2: Using `AnyMachine`
+//! Results are stored type-erased, but every cog gets a handle typed to its own result.
 //!
 //! ```
-//! use rustycog::Machine;
-//! use std::any::Any;
-//!
-//! let mut any_machine = Machine::<Box<dyn Any + Send>>::powered(4);
-//! let id = any_machine.insert_cog(|| Box::new(42));
+//! use rustycog::AnyMachine;
 //!
-//! let result = any_machine.wait_for_result(id).unwrap();
+//! let any_machine = AnyMachine::powered(4);
+//! let number = any_machine.insert_cog(|| 42);
+//! let flag = any_machine.insert_cog(|| true);
 //!
-//! if let Some(value) = result.downcast_ref::<i32>() {
-//!     println!("Got an i32: {}", value);
-//! } else {
-//!     println!("Unknown type");
-//! }
+//! assert_eq!(number.wait(), Ok(42));
+//! assert_eq!(flag.wait(), Ok(true));
 //! ```
 //!
 //! ## Error Handling
 //! RustyCog provides error handling through MachineError and `CogError`.
 
 mod cog;
+mod completions;
+mod context;
 mod engine;
 pub mod error;
+mod handle;
 mod machine;
+mod panic;
+mod queue;
+mod scope;
+mod token;
 pub mod types;
+mod untyped;
+mod watch;
 
+#[doc(inline)]
+pub use crate::completions::Completions;
+#[doc(inline)]
+pub use crate::context::CogContext;
+#[doc(inline)]
+pub use crate::handle::CogHandle;
 #[doc(inline)]
 pub use crate::machine::Machine;
+#[doc(inline)]
+pub use crate::scope::Scope;
+#[doc(inline)]
+pub use crate::token::CancelToken;
+#[doc(inline)]
+pub use crate::untyped::AnyMachine;
@@ -1,42 +1,88 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 use crate::error::MachineError;
 use crate::{
-    cog::{Cog, CogState},
-    engine::Engine,
+    cog::{ArcMutexCog, Cog, CogFn, CogMap, CogOptions},
+    completions::Completions,
+    context::CogContext,
+    engine::{self, Engine, EngineList, LocalQueue, Retirement},
     error::CogError,
-    types::{CogId, CogType, EngineId},
+    handle::CogHandle,
+    scope::Scope,
+    token::CancelToken,
+    types::{CogId, CogType, MachineId, Priority, RetryPolicy, ShutdownMode, ShutdownReport},
+    watch::{Outstanding, Watch},
 };
 
-type CogFn<T> = Box<dyn FnOnce() -> T + Send + std::panic::UnwindSafe + 'static>;
-type ArcMutexCog<T> = Arc<Mutex<Cog<T, CogFn<T>>>>;
+static MACHINE_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// How long a thread helping out while it waits sleeps when there is no work to help with
+const HELP_INTERVAL: Duration = Duration::from_millis(1);
 
 /// RustyCogs task manager
 ///
 /// The Machine manages the engine (worker) and cogs (tasks)
 /// and provides some basic methods to initialize and insert cogs,
 /// as well as retrieving their results.
+///
+/// Every method takes `&self`, so a machine can be shared between threads
+/// (for example in an `Arc`) that insert cogs and retrieve results concurrently.
+///
+/// # Example
+/// ```
+/// use rustycog::Machine;
+/// use std::sync::Arc;
+///
+/// let machine = Arc::new(Machine::powered(4));
+///
+/// let producers: Vec<_> = (0..4)
+///     .map(|i| {
+///         let machine = machine.clone();
+///         std::thread::spawn(move || {
+///             let id = machine.insert_cog(move || i * 10);
+///             machine.wait_for_result(id)
+///         })
+///     })
+///     .collect();
+///
+/// for (i, producer) in producers.into_iter().enumerate() {
+///     assert_eq!(producer.join().unwrap(), Ok(i * 10));
+/// }
+/// ```
 pub struct Machine<T>
 where
     T: CogType,
 {
-    cog_id: CogId,
-    engine_id: EngineId,
+    core: Arc<Core<T>>,
+}
+
+/// The state of a machine, shared with the handles and contexts of its cogs
+pub struct Core<T>
+where
+    T: CogType,
+{
+    pub id: MachineId,
+    cog_id: AtomicUsize,
+    engine_id: AtomicUsize,
 
-    cogs: HashMap<CogId, ArcMutexCog<T>>,
+    pub cogs: CogMap<T>,
+    outstanding: Arc<Outstanding>,
 
+    powered: AtomicBool,
+    paused: Arc<AtomicBool>,
+    min_engines: u32,
     max_engines: u32,
-    engines: Arc<RwLock<Vec<Arc<RwLock<Engine<T>>>>>>,
+    keep_alive: Option<Duration>,
+    engines: EngineList<T>,
     work: Arc<(Mutex<bool>, Condvar)>,
 }
 
 impl<T: CogType> Drop for Machine<T> {
     fn drop(&mut self) {
-        let engines = std::mem::take(&mut self.engines);
-        for engine in engines.read().unwrap().iter() {
-            engine.write().unwrap().kill();
-        }
+        self.shutdown(ShutdownMode::Abort);
     }
 }
 
@@ -55,18 +101,65 @@ impl<T: CogType> Machine<T> {
     /// let i32_machine = Machine::<i32>::powered(4);
     /// ```
     pub fn powered(max_engines: u32) -> Self {
-        let mut machine = Self {
-            cog_id: 0,
-            engine_id: 0,
+        let machine = Self::new(max_engines, max_engines, None);
+        machine.power().expect("A new machine is cold");
+        machine
+    }
 
+    /// Creates a new, powered Machine that scales its engines with the workload
+    ///
+    /// The machine starts with `min_engines` engines and spawns more, up to `max_engines`,
+    /// when cogs queue up faster than the engines can run them.
+    /// Engines that have been idle for `keep_alive` are shut down again,
+    /// until only `min_engines` engines remain.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::dynamic(1, 4, Duration::from_millis(10));
+    /// assert_eq!(machine.engine_count(), 1);
+    ///
+    /// let ids: Vec<_> = (0..16)
+    ///     .map(|_| machine.insert_cog(|| std::thread::sleep(Duration::from_millis(5))))
+    ///     .collect();
+    /// assert!(machine.engine_count() > 1);
+    ///
+    /// for id in ids {
+    ///     machine.wait_for_result(id).unwrap();
+    /// }
+    /// std::thread::sleep(Duration::from_millis(200));
+    /// assert_eq!(machine.engine_count(), 1);
+    /// ```
+    ///
+    /// Engines also retire while work keeps trickling in, as long as it is too little for them:
+    /// ```
+    /// use rustycog::Machine;
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::dynamic(1, 4, Duration::from_millis(60));
+    /// let ids: Vec<_> = (0..16)
+    ///     .map(|_| machine.insert_cog(|| std::thread::sleep(Duration::from_millis(5))))
+    ///     .collect();
+    /// let peak = machine.engine_count();
+    /// assert!(peak > 1);
+    /// machine.wait_all(&ids);
+    ///
+    /// for _ in 0..25 {
+    ///     let id = machine.insert_cog(|| ());
+    ///     machine.wait_for_result(id).unwrap();
+    ///     std::thread::sleep(Duration::from_millis(40));
+    /// }
+    /// assert!(machine.engine_count() < peak);
+    /// ```
+    pub fn dynamic(min_engines: u32, max_engines: u32, keep_alive: Duration) -> Self {
+        let machine = Self::new(
+            u32::min(min_engines, max_engines),
             max_engines,
-
-            engines: Arc::new(RwLock::new(Vec::new())),
-            cogs: HashMap::new(),
-            work: Arc::new((Mutex::new(false), Condvar::new())),
-        };
-
-        machine.spawn_engines(max_engines);
+            Some(keep_alive),
+        );
+        machine.power().expect("A new machine is cold");
         machine
     }
 
@@ -85,21 +178,34 @@ impl<T: CogType> Machine<T> {
     /// let i32_machine = Machine::<i32>::cold(4);
     /// ```
     pub fn cold(max_engines: u32) -> Self {
+        Self::new(max_engines, max_engines, None)
+    }
+
+    fn new(min_engines: u32, max_engines: u32, keep_alive: Option<Duration>) -> Self {
         Self {
-            cog_id: 0,
-            engine_id: 0,
+            core: Arc::new(Core {
+                id: MACHINE_ID.fetch_add(1, Ordering::Relaxed),
+                cog_id: AtomicUsize::new(0),
+                engine_id: AtomicUsize::new(0),
 
-            cogs: HashMap::new(),
+                cogs: Mutex::new(HashMap::new()),
+                outstanding: Arc::new(Outstanding::new()),
 
-            max_engines,
-            engines: Arc::new(RwLock::new(Vec::new())),
-            work: Arc::new((Mutex::new(false), Condvar::new())),
+                powered: AtomicBool::new(false),
+                paused: Arc::new(AtomicBool::new(false)),
+                min_engines,
+                max_engines,
+                keep_alive,
+                engines: Arc::new(RwLock::new(Vec::new())),
+                work: Arc::new((Mutex::new(false), Condvar::new())),
+            }),
         }
     }
 
     /// Power on a cold Machine
     ///
     /// A machine being powered means the machine can run cogs.
+    /// Cogs inserted while the machine was cold are queued on the engines right away.
     ///
     /// # Errors
     /// This function will return an error if:
@@ -108,7 +214,7 @@ impl<T: CogType> Machine<T> {
     /// # Example
     /// ```
     /// use rustycog::{Machine, error::MachineError};
-    /// let mut machine = Machine::<i32>::cold(4);
+    /// let machine = Machine::<i32>::cold(4);
     ///
     /// let powered = machine.power();
     /// assert_eq!(powered, Ok(()));
@@ -116,25 +222,115 @@ impl<T: CogType> Machine<T> {
     /// let powered = machine.power();
     /// assert_eq!(powered, Err(MachineError::AlreadyPowered));
     /// ```
-    pub fn power(&mut self) -> Result<(), MachineError> {
-        if self.engines.read().unwrap().len() == 0 {
-            self.spawn_engines(self.max_engines);
-            Ok(())
-        } else {
-            Err(MachineError::AlreadyPowered)
+    ///
+    /// Powering a machine back up after shutting it down:
+    /// ```
+    /// use rustycog::{Machine, types::ShutdownMode};
+    /// let machine = Machine::powered(4);
+    ///
+    /// machine.shutdown(ShutdownMode::Drain);
+    /// let id = machine.insert_cog(|| 42);
+    ///
+    /// machine.power().unwrap();
+    /// assert_eq!(machine.engine_count(), 4);
+    /// assert_eq!(machine.wait_for_result(id), Ok(42));
+    /// ```
+    pub fn power(&self) -> Result<(), MachineError> {
+        if self.core.powered.swap(true, Ordering::SeqCst) {
+            return Err(MachineError::AlreadyPowered);
         }
+        self.core.spawn_engines(self.core.min_engines);
+        // Cogs inserted while the machine was cold have not been queued on any engine yet
+        self.core.distribute_released(self.core.waiting_cogs());
+        Ok(())
     }
 
-    fn spawn_engines(&mut self, amount: u32) {
-        for _ in 0..amount {
-            let engines = self.engines.clone();
-            self.engines.write().unwrap().push(Engine::new(
-                self.engine_id,
-                engines,
-                self.work.clone(),
-            ));
-            self.engine_id += 1;
+    /// Pause the machine
+    ///
+    /// Engines stop picking up cogs (tasks), until the machine is resumed with
+    /// `Machine::resume`. Cogs that are already running are allowed to finish, and cogs
+    /// inserted while paused are queued as usual. Unlike shutting the machine down,
+    /// the engines keep running, so nothing queued is lost.
+    ///
+    /// # Notes
+    /// - Threads waiting on a cog do not run it themselves while the machine is paused,
+    ///   so they wait until the machine is resumed. This includes cogs joined by running cogs.
+    /// - Shutting the machine down with `ShutdownMode::Drain` resumes it,
+    ///   while `ShutdownMode::Abort` leaves it paused.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The machine is already paused (`MachineError::AlreadyPaused`)
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::powered(4);
+    /// machine.pause().unwrap();
+    ///
+    /// let id = machine.insert_cog(|| 42);
+    /// std::thread::sleep(Duration::from_millis(50));
+    /// assert_eq!(machine.get_result(id), Err(CogError::NotCompleted(id)));
+    ///
+    /// machine.resume().unwrap();
+    /// assert_eq!(machine.wait_for_result(id), Ok(42));
+    /// ```
+    pub fn pause(&self) -> Result<(), MachineError> {
+        if self.core.paused.swap(true, Ordering::SeqCst) {
+            return Err(MachineError::AlreadyPaused);
+        }
+        Ok(())
+    }
+
+    /// Resume a paused machine
+    ///
+    /// The engines continue running the cogs (tasks) that were queued while the machine
+    /// was paused.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The machine is not paused (`MachineError::NotPaused`)
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::MachineError};
+    /// let machine = Machine::<i32>::powered(4);
+    ///
+    /// machine.pause().unwrap();
+    /// assert!(machine.is_paused());
+    ///
+    /// assert_eq!(machine.resume(), Ok(()));
+    /// assert_eq!(machine.resume(), Err(MachineError::NotPaused));
+    /// ```
+    pub fn resume(&self) -> Result<(), MachineError> {
+        if !self.core.paused.swap(false, Ordering::SeqCst) {
+            return Err(MachineError::NotPaused);
         }
+        self.core.notify_work();
+        Ok(())
+    }
+
+    /// Whether the machine is paused, see `Machine::pause`
+    pub fn is_paused(&self) -> bool {
+        self.core.is_paused()
+    }
+
+    /// The amount of engines currently running
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    ///
+    /// let machine = Machine::<i32>::cold(4);
+    /// assert_eq!(machine.engine_count(), 0);
+    ///
+    /// machine.power().unwrap();
+    /// assert_eq!(machine.engine_count(), 4);
+    /// ```
+    pub fn engine_count(&self) -> usize {
+        self.core.engines.read().unwrap().len()
     }
 
     /// Insert a cog into the machine
@@ -145,199 +341,1722 @@ impl<T: CogType> Machine<T> {
     /// ```
     /// use rustycog::Machine;
     ///
-    /// let mut machine = Machine::powered(4);
+    /// let machine = Machine::powered(4);
     ///
     /// let cog1_id = machine.insert_cog(|| {0});
     /// let cog2_id = machine.insert_cog(|| {1});
     /// ```
-    pub fn insert_cog<F>(&mut self, func: F) -> CogId
+    pub fn insert_cog<F>(&self, func: F) -> CogId
     where
         F: FnOnce() -> T + Send + std::panic::UnwindSafe + 'static,
     {
-        let id = self.cog_id;
-        let cog: ArcMutexCog<T> = Arc::new(Mutex::new(Cog::new(id, Box::new(func))));
-        self.cogs.insert(id, cog.clone());
-        self.distribute_cog(cog);
-
-        self.cog_id += 1;
-        id
+        self.core
+            .insert_boxed_cog(Box::new(func), CogOptions::default())
     }
 
-    pub fn insert_cog_batch<F>(&mut self, funcs: Vec<F>) -> CogId
+    /// Insert a cog into the machine, returning a handle to it
+    ///
+    /// Works like `Machine::insert_cog`, but the returned `CogHandle` can retrieve the
+    /// result from any thread, without access to the machine.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    ///
+    /// let machine = Machine::powered(4);
+    /// let handle = machine.insert_cog_with_handle(|| 42);
+    ///
+    /// let result = std::thread::spawn(move || handle.wait()).join().unwrap();
+    /// assert_eq!(result, Ok(42));
+    /// ```
+    pub fn insert_cog_with_handle<F>(&self, func: F) -> CogHandle<T>
     where
         F: FnOnce() -> T + Send + std::panic::UnwindSafe + 'static,
     {
-        let id = self.cog_id;
-        let mut cog_batch = Vec::new();
-        for func in funcs {
-            let cog: ArcMutexCog<T> = Arc::new(Mutex::new(Cog::new(id, Box::new(func))));
-            self.cogs.insert(id, cog.clone());
-            cog_batch.push(cog);
-        }
-        self.distribute_cog_batch(cog_batch);
-
-        self.cog_id += 1;
-        id
+        let id = self.insert_cog(func);
+        self.handle(id).expect("The cog was just inserted")
     }
 
-    fn distribute_cog(&self, cog: ArcMutexCog<T>) {
-        let cog_id = cog.lock().unwrap().id;
-        if self.engines.read().unwrap().len() > 0 {
-            let engine =
-                self.engines.read().unwrap()[cog_id % self.engines.read().unwrap().len()].clone();
-            let engine = engine.write().unwrap();
-            engine.local_queue.write().unwrap().push_back(cog);
-
-            self.notify_work();
-        }
+    /// Get a handle to a cog (task) in the machine
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The cog is not in the machine (`CogError::NotInserted`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    ///
+    /// let machine = Machine::powered(4);
+    /// let id = machine.insert_cog(|| 42);
+    ///
+    /// let handle = machine.handle(id).unwrap();
+    /// assert_eq!(handle.id(), id);
+    /// assert_eq!(handle.machine_id(), machine.id());
+    /// assert_eq!(handle.wait(), Ok(42));
+    /// ```
+    pub fn handle(&self, id: CogId) -> Result<CogHandle<T>, CogError> {
+        Ok(CogHandle::new(id, self.core.cog(id)?, &self.core))
     }
 
-    fn distribute_cog_batch(&self, cogs: Vec<ArcMutexCog<T>>) {
-        let cog_id = cogs[0].lock().unwrap().id;
-        if self.engines.read().unwrap().len() > 0 {
-            let engine =
-                self.engines.read().unwrap()[cog_id % self.engines.read().unwrap().len()].clone();
-            let engine = engine.write().unwrap();
-            engine.local_queue.write().unwrap().extend(cogs);
-
-            self.notify_work();
-        }
+    /// The identity of the machine, unique within the process
+    pub fn id(&self) -> MachineId {
+        self.core.id
     }
 
-    fn notify_work(&self) {
-        let (lock, cvar) = &*self.work;
-        let mut work = lock.lock().unwrap();
-        *work = true;
-        cvar.notify_all();
+    /// Insert a cog that can spawn more cogs while it is running
+    ///
+    /// The cog receives a `CogContext`, through which it can spawn child cogs.
+    /// Children are queued on the engine running their parent, from where idle engines
+    /// steal them, which suits recursive divide-and-conquer algorithms.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{CogContext, Machine};
+    ///
+    /// fn sum(context: CogContext<u64>, start: u64, end: u64) -> u64 {
+    ///     if end - start <= 1_000 {
+    ///         return (start..end).sum();
+    ///     }
+    ///     let middle = start + (end - start) / 2;
+    ///     let left = context.spawn(move |context| sum(context, start, middle));
+    ///     let right = sum(context, middle, end);
+    ///     left.wait().unwrap() + right
+    /// }
+    ///
+    /// let machine = Machine::powered(4);
+    /// let id = machine.insert_cog_with_context(|context| sum(context, 0, 100_000));
+    ///
+    /// assert_eq!(machine.wait_for_result(id), Ok((0..100_000).sum()));
+    /// ```
+    pub fn insert_cog_with_context<F>(&self, func: F) -> CogId
+    where
+        F: FnOnce(CogContext<T>) -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        let context = CogContext::new(&self.core);
+        self.core
+            .insert_boxed_cog(Box::new(move || func(context)), CogOptions::default())
     }
 
-    /// Retrieves the result of a cog (task) by its ID, removing the cog once the result is
-    /// retrieved.
+    /// Run two closures, possibly in parallel, and return both results
+    ///
+    /// `a` and `b` are inserted as cogs, with the calling thread running them itself if no
+    /// engine has picked them up yet, like `Machine::wait_for_result` does.
+    /// Only engines keep running other queued cogs while they wait, so joins can be nested
+    /// inside cogs without running out of engines. Inside a cog, use `CogContext::join` instead.
     ///
     /// # Errors
     /// This function will return an error if:
-    /// - The cog has not been added to the machine (`CogError::NotFound`).
-    /// - The cog has already been retrieved (`CogError::NotFound`).
-    /// - The cog has not completed (`CogError::NotCompleted`).
-    /// - The cog panicked (`CogError::Panicked`).
+    /// - Either closure panicked (`CogError::Panicked`), in which case the other one
+    ///   has still finished.
     ///
     /// # Example
-    /// NOTE: The example uses wait_for_result() to retrieve the result of the cog.
-    /// This is to keep the program running synchronously
-    ///
     /// ```
     /// use rustycog::Machine;
-    /// use rustycog::error::CogError;
     ///
-    /// let mut machine = Machine::powered(4);
-    /// let id = machine.insert_cog(|| 42);
+    /// let machine = Machine::<i32>::powered(2);
+    /// let (left, right) = machine.join(|| (0..1_000).sum(), || (1_000..2_000).sum()).unwrap();
     ///
-    /// // First retrieval - succeeds
-    /// assert_eq!(machine.wait_for_result(id), Ok(42));
+    /// assert_eq!(left + right, (0..2_000).sum());
+    /// ```
     ///
-    /// // Second retrieval - cog is already removed
-    /// assert_eq!(machine.wait_for_result(id), Err(CogError::NotInserted(id)));
-    pub fn get_result(&mut self, id: CogId) -> Result<T, CogError> {
-        let result = match self.cogs.get(&id) {
-            Some(cog) => cog.lock().unwrap().get_result(),
-            None => Err(CogError::NotInserted(id)),
-        };
-        match result {
-            Ok(_) | Err(CogError::Panicked(_)) => {
-                self.cogs.remove(&id);
-            }
-            _ => (),
-        }
-        result
+    /// Outside of a cog, other queued cogs are left to the engines:
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let machine = Machine::powered(1);
+    /// let (finish_tx, finish_rx) = channel::<()>();
+    /// let busy_id = machine.insert_cog(move || {
+    ///     finish_rx.recv().unwrap();
+    ///     0
+    /// });
+    /// let queued_id = machine.insert_cog(|| 3);
+    ///
+    /// assert_eq!(machine.join(|| 1, || 2), Ok((1, 2)));
+    /// assert_eq!(machine.get_result(queued_id), Err(CogError::NotCompleted(queued_id)));
+    ///
+    /// finish_tx.send(()).unwrap();
+    /// assert_eq!(machine.wait_for_result(busy_id), Ok(0));
+    /// assert_eq!(machine.wait_for_result(queued_id), Ok(3));
+    /// ```
+    pub fn join<A, B>(&self, a: A, b: B) -> Result<(T, T), CogError>
+    where
+        A: FnOnce() -> T + Send + std::panic::UnwindSafe + 'static,
+        B: FnOnce() -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        self.core.join(Box::new(a), Box::new(b))
     }
 
-    /// Waits for the result of a cog (task) by its ID, removing the cog once the result is
-    /// retrieved.
+    /// Run cogs that borrow from the stack
     ///
-    /// # Errors
-    /// This function will return an error if:
-    /// - The cog has not been added to the machine (`CogError::NotFound`).
-    /// - The cog panicked (`CogError::Panicked`).
+    /// Cogs inserted through the `Scope` do not have to be `'static`.
+    /// Like `std::thread::scope`, this function does not return before every cog inserted
+    /// through the scope has finished, even if `f` panics.
     ///
     /// # Example
     /// ```
     /// use rustycog::Machine;
-    /// use rustycog::error::CogError;
     ///
-    /// let mut machine = Machine::powered(4);
+    /// let machine = Machine::<i32>::powered(4);
+    /// let numbers: Vec<i32> = (1..=100).collect();
     ///
-    /// let cog1_id = machine.insert_cog(|| {0});
-    /// let cog2_id = machine.insert_cog(|| {
-    ///     panic!("I paniced :(");
-    ///     0
+    /// let sum: i32 = machine.scope(|scope| {
+    ///     let handles: Vec<_> = numbers
+    ///         .chunks(10)
+    ///         .map(|chunk| scope.insert_cog(move || chunk.iter().sum()))
+    ///         .collect();
+    ///     handles.into_iter().map(|handle| handle.wait().unwrap()).sum()
     /// });
     ///
-    /// assert_eq!(machine.wait_for_result(cog1_id), Ok(0));
-    /// assert_eq!(machine.wait_for_result(cog2_id), Err(CogError::Panicked(cog2_id)));
-    /// // Second retrieval - cog is already removed
-    /// assert_eq!(machine.wait_for_result(cog2_id), Err(CogError::NotInserted(cog2_id)));
+    /// assert_eq!(sum, 5050);
     /// ```
-    pub fn wait_for_result(&mut self, id: CogId) -> Result<T, CogError> {
-        let cog = self.cogs.get(&id).ok_or(CogError::NotInserted(id))?;
-
-        {
-            let locked_cog = cog.lock().unwrap();
-            if let CogState::Waiting = &locked_cog.state {
-                let (lock, cvar) = &*locked_cog.done.clone();
-                // Let the cog be run
-                drop(locked_cog);
-
-                let mut started = lock.lock().unwrap();
-                while !*started {
-                    started = cvar.wait(started).unwrap();
-                }
-            };
+    pub fn scope<'env, F, R>(&self, f: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env, T>) -> R,
+    {
+        let scope = Scope::new(self.core.clone());
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(&scope)));
+        scope.wait();
+        match result {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
         }
+    }
 
-        let result = cog.lock().unwrap().get_result();
+    /// Insert a cog with a priority into the machine
+    ///
+    /// Cogs with a higher priority are run before cogs with a lower priority,
+    /// no matter when they were inserted. `Machine::insert_cog` uses `Priority::Normal`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, types::Priority};
+    ///
+    /// let machine = Machine::powered(4);
+    ///
+    /// let bulk_id = machine.insert_cog_with_priority(|| 0, Priority::Low);
+    /// let urgent_id = machine.insert_cog_with_priority(|| 1, Priority::High);
+    ///
+    /// assert_eq!(machine.wait_for_result(urgent_id), Ok(1));
+    /// assert_eq!(machine.wait_for_result(bulk_id), Ok(0));
+    /// ```
+    pub fn insert_cog_with_priority<F>(&self, func: F, priority: Priority) -> CogId
+    where
+        F: FnOnce() -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        self.core.insert_boxed_cog(
+            Box::new(func),
+            CogOptions {
+                priority,
+                ..CogOptions::default()
+            },
+        )
+    }
 
-        if matches!(result, Ok(_) | Err(CogError::Panicked(_))) {
-            self.cogs.remove(&id);
-        }
-        result
+    /// Insert a cog that is run again if it panics
+    ///
+    /// `factory` is called again, up to `policy.retries` times, for as long as it keeps
+    /// panicking. The backoff between retries is waited out on the engine running the cog.
+    /// Retrieving the result returns `CogError::Panicked` only if the last retry panicked as well.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, types::RetryPolicy};
+    /// use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    ///
+    /// static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+    ///
+    /// let machine = Machine::powered(1);
+    /// let id = machine.insert_cog_with_retry(
+    ///     || {
+    ///         if ATTEMPTS.fetch_add(1, Ordering::SeqCst) < 2 {
+    ///             panic!("Flaky connection");
+    ///         }
+    ///         42
+    ///     },
+    ///     RetryPolicy::new(3),
+    /// );
+    ///
+    /// assert_eq!(machine.wait_for_result(id), Ok(42));
+    /// assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 3);
+    /// ```
+    ///
+    /// The backoff is waited out before every retry:
+    /// ```
+    /// use rustycog::{Machine, types::{Backoff, RetryPolicy}};
+    /// use std::{sync::Mutex, time::{Duration, Instant}};
+    ///
+    /// static ATTEMPTS: Mutex<Vec<Instant>> = Mutex::new(Vec::new());
+    ///
+    /// let machine = Machine::powered(1);
+    /// let backoff = Backoff::Exponential(Duration::from_millis(20));
+    /// let id = machine.insert_cog_with_retry(
+    ///     || {
+    ///         let mut attempts = ATTEMPTS.lock().unwrap();
+    ///         attempts.push(Instant::now());
+    ///         if attempts.len() < 4 {
+    ///             drop(attempts);
+    ///             panic!("Flaky connection");
+    ///         }
+    ///         42
+    ///     },
+    ///     RetryPolicy::new(3).with_backoff(backoff),
+    /// );
+    ///
+    /// assert_eq!(machine.wait_for_result(id), Ok(42));
+    /// let attempts = ATTEMPTS.lock().unwrap();
+    /// for (retry, pair) in attempts.windows(2).enumerate() {
+    ///     assert!(pair[1] - pair[0] >= backoff.delay(retry as u32));
+    /// }
+    /// ```
+    pub fn insert_cog_with_retry<F>(&self, factory: F, policy: RetryPolicy) -> CogId
+    where
+        F: Fn() -> T + Send + std::panic::UnwindSafe + std::panic::RefUnwindSafe + 'static,
+    {
+        let func = move || {
+            let mut retry = 0;
+            loop {
+                match std::panic::catch_unwind(&factory) {
+                    Ok(result) => return result,
+                    Err(_err) if retry < policy.retries => {
+                        std::thread::sleep(policy.backoff.delay(retry));
+                        retry += 1;
+                    }
+                    Err(err) => std::panic::resume_unwind(err),
+                }
+            }
+        };
+        self.core
+            .insert_boxed_cog(Box::new(func), CogOptions::default())
     }
 
-    /// Wait for the machine (task manager) to finish
+    /// Insert a cog that must start running before a deadline
     ///
-    /// Pause execution until the machine has finished running
-    /// all of its cogs (tasks)
+    /// If the cog is still waiting to be engaged when `deadline` passes, it is never run
+    /// and retrieving its result returns `CogError::DeadlineExceeded`.
+    /// A cog that has already started running is allowed to finish.
     ///
     /// # Example
-    /// ```ignore
+    /// ```
     /// use rustycog::{Machine, error::CogError};
-    /// let mut machine = Machine::powered(4);
+    /// use std::time::{Duration, Instant};
     ///
-    /// for i in 0..1000 {
-    ///     machine.insert_cog(move || i);
-    /// }
+    /// let machine = Machine::cold(1);
+    /// let stale_id = machine.insert_cog_with_deadline(|| 0, Instant::now());
+    /// let fresh_id =
+    ///     machine.insert_cog_with_deadline(|| 1, Instant::now() + Duration::from_secs(60));
     ///
-    /// let result = 111111;
+    /// machine.power().unwrap();
+    /// assert_eq!(
+    ///     machine.wait_for_result(stale_id),
+    ///     Err(CogError::DeadlineExceeded(stale_id))
+    /// );
+    /// assert_eq!(machine.wait_for_result(fresh_id), Ok(1));
+    /// ```
+    pub fn insert_cog_with_deadline<F>(&self, func: F, deadline: Instant) -> CogId
+    where
+        F: FnOnce() -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        self.core.insert_boxed_cog(
+            Box::new(func),
+            CogOptions {
+                deadline: Some(deadline),
+                ..CogOptions::default()
+            },
+        )
+    }
+
+    /// Insert a cog that can be asked to stop while it is running
     ///
-    /// let last_id = machine.insert_cog(move || {
-    ///     std::thread::sleep(std::time::Duration::from_secs(1));
-    ///     result
+    /// The cog receives a `CancelToken` which is flipped by `Machine::request_cancel`.
+    /// If the token has been flipped when the cog returns, its result is discarded and
+    /// retrieving it returns `CogError::Cancelled`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, CancelToken, error::CogError};
+    ///
+    /// let machine = Machine::powered(1);
+    /// let (started_tx, started_rx) = std::sync::mpsc::channel();
+    ///
+    /// let id = machine.insert_cog_with_token(move |token: CancelToken| {
+    ///     started_tx.send(()).unwrap();
+    ///     while !token.is_cancelled() {
+    ///         std::thread::yield_now();
+    ///     }
+    ///     0
     /// });
     ///
-    /// // Wait for all tasks
-    /// assert_eq!(machine.get_result(last_id), Err(CogError::NotCompleted(last_id)));
-    /// machine.wait_until_done();
-    /// assert_eq!(machine.get_result(last_id), Ok(result));
+    /// started_rx.recv().unwrap();
+    /// assert_eq!(machine.request_cancel(id), Ok(()));
+    /// assert_eq!(machine.wait_for_result(id), Err(CogError::Cancelled(id)));
     /// ```
-    pub fn wait_until_done(&mut self) {
-        loop {
-            for (_, cog) in self.cogs.iter() {
-                if let CogState::Done(_) = &cog.lock().unwrap().state {
-                } else {
-                    // std::thread::sleep(std::time::Duration::from_millis(1));
-                    continue;
-                }
-            }
-            return;
+    pub fn insert_cog_with_token<F>(&self, func: F) -> CogId
+    where
+        F: FnOnce(CancelToken) -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        let token = CancelToken::new();
+        let cog_token = token.clone();
+        self.core.insert_boxed_cog(
+            Box::new(move || func(cog_token)),
+            CogOptions {
+                token: Some(token),
+                ..CogOptions::default()
+            },
+        )
+    }
+
+    /// Insert a cog that only runs after other cogs have finished
+    ///
+    /// The cog is held back, outside of the engines, until every prerequisite has completed.
+    /// If a prerequisite panics, is cancelled or fails itself, the cog is never run and
+    /// retrieving its result returns `CogError::DependencyFailed`.
+    /// Once the result of a cog has been retrieved, it is no longer known whether it
+    /// succeeded, so it can not be a prerequisite anymore.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - A prerequisite is not in the machine, because it was never added or its result has
+    ///   already been retrieved (`CogError::NotInserted`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::powered(4);
+    ///
+    /// let compile_id = machine.insert_cog(|| 1);
+    /// let fetch_id = machine.insert_cog(|| panic!("Network is down"));
+    ///
+    /// let link_id = machine.insert_cog_after(&[compile_id], || 2).unwrap();
+    /// let deploy_id = machine.insert_cog_after(&[link_id, fetch_id], || 3).unwrap();
+    ///
+    /// assert_eq!(machine.wait_for_result(link_id), Ok(2));
+    /// assert_eq!(
+    ///     machine.wait_for_result(deploy_id),
+    ///     Err(CogError::DependencyFailed(deploy_id, fetch_id))
+    /// );
+    ///
+    /// // The panic of `fetch_id` has been retrieved, so it can no longer hold cogs back
+    /// assert!(machine.wait_for_result(fetch_id).is_err());
+    /// assert_eq!(
+    ///     machine.insert_cog_after(&[fetch_id], || 4),
+    ///     Err(CogError::NotInserted(fetch_id))
+    /// );
+    /// ```
+    ///
+    /// Without engines, the waiting thread runs the prerequisites as well:
+    /// ```
+    /// use rustycog::Machine;
+    ///
+    /// let machine = Machine::cold(2);
+    ///
+    /// let first_id = machine.insert_cog(|| 1);
+    /// let second_id = machine.insert_cog_after(&[first_id], || 2).unwrap();
+    /// let third_id = machine.insert_cog_after(&[second_id], || 3).unwrap();
+    ///
+    /// assert_eq!(machine.wait_all(&[third_id, first_id]), vec![Ok(3), Ok(1)]);
+    /// ```
+    pub fn insert_cog_after<F>(&self, prerequisites: &[CogId], func: F) -> Result<CogId, CogError>
+    where
+        F: FnOnce() -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        let cogs = self.core.cogs.lock().unwrap();
+        let prerequisites = prerequisites
+            .iter()
+            .map(|id| cogs.get(id).cloned().ok_or(CogError::NotInserted(*id)))
+            .collect::<Result<_, _>>()?;
+        drop(cogs);
+        Ok(self
+            .core
+            .insert_dependent_cog(Box::new(func), prerequisites))
+    }
+
+    /// Insert a cog that runs on the result of another cog
+    ///
+    /// The cog is held back until the prerequisite has completed and then receives its result.
+    /// The result is handed over once the new cog runs, so the prerequisite can not be
+    /// retrieved on its own. If the new cog never runs, e.g. because it was cancelled,
+    /// the prerequisite keeps its result.
+    /// If the prerequisite panics or is cancelled, retrieving the result of the new cog returns
+    /// `CogError::DependencyFailed`.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The prerequisite is not in the machine (`CogError::NotInserted`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::powered(4);
+    ///
+    /// let parse_id = machine.insert_cog(|| 20);
+    /// let double_id = machine.insert_cog_then(parse_id, |parsed| parsed * 2).unwrap();
+    /// let add_id = machine.insert_cog_then(double_id, |doubled| doubled + 2).unwrap();
+    ///
+    /// assert_eq!(machine.wait_for_result(add_id), Ok(42));
+    /// assert_eq!(machine.get_result(parse_id), Err(CogError::NotInserted(parse_id)));
+    /// ```
+    ///
+    /// Other cogs can still wait for a prerequisite whose result is handed over:
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    /// use std::{sync::mpsc::channel, time::Duration};
+    ///
+    /// let machine = Machine::powered(2);
+    /// let (finish_tx, finish_rx) = channel::<()>();
+    ///
+    /// let slow_id = machine.insert_cog(move || {
+    ///     finish_rx.recv().unwrap();
+    ///     1
+    /// });
+    /// let double_id = machine.insert_cog_then(slow_id, |slow| slow * 2).unwrap();
+    /// let after_id = machine.insert_cog_after(&[slow_id], || 0).unwrap();
+    ///
+    /// std::thread::sleep(Duration::from_millis(50));
+    /// assert_eq!(machine.get_result(after_id), Err(CogError::NotCompleted(after_id)));
+    ///
+    /// finish_tx.send(()).unwrap();
+    /// assert_eq!(machine.wait_for_result(double_id), Ok(2));
+    /// assert_eq!(machine.wait_for_result(after_id), Ok(0));
+    /// ```
+    pub fn insert_cog_then<F>(&self, prerequisite: CogId, func: F) -> Result<CogId, CogError>
+    where
+        F: FnOnce(T) -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        self.insert_cog_then_all(&[prerequisite], move |mut results| func(results.remove(0)))
+    }
+
+    /// Insert a cog that runs on the results of several other cogs
+    ///
+    /// Works like `Machine::insert_cog_then`, with the results passed to the cog in the same
+    /// order as `prerequisites`.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - A prerequisite is not in the machine, or is listed more than once
+    ///   (`CogError::NotInserted`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    ///
+    /// let machine = Machine::powered(4);
+    ///
+    /// let ids: Vec<_> = (1..=4).map(|i| machine.insert_cog(move || i * i)).collect();
+    /// let sum_id = machine
+    ///     .insert_cog_then_all(&ids, |squares| squares.into_iter().sum())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(machine.wait_for_result(sum_id), Ok(30));
+    /// ```
+    ///
+    /// When one prerequisite fails, the others keep their results:
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    /// use std::sync::mpsc::channel;
+    ///
+    /// let machine = Machine::powered(2);
+    /// let (finish_tx, finish_rx) = channel::<()>();
+    ///
+    /// let ok_id = machine.insert_cog(|| 1);
+    /// let failed_id = machine.insert_cog(|| panic!("Oh no!"));
+    /// let slow_id = machine.insert_cog(move || {
+    ///     finish_rx.recv().unwrap();
+    ///     3
+    /// });
+    /// let sum_id = machine
+    ///     .insert_cog_then_all(&[ok_id, failed_id, slow_id], |results| results.into_iter().sum())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     machine.wait_for_result(sum_id),
+    ///     Err(CogError::DependencyFailed(sum_id, failed_id))
+    /// );
+    /// finish_tx.send(()).unwrap();
+    /// assert_eq!(machine.wait_for_result(ok_id), Ok(1));
+    /// assert!(matches!(machine.wait_for_result(failed_id), Err(CogError::Panicked(..))));
+    /// assert_eq!(machine.wait_for_result(slow_id), Ok(3));
+    /// ```
+    pub fn insert_cog_then_all<F>(
+        &self,
+        prerequisites: &[CogId],
+        func: F,
+    ) -> Result<CogId, CogError>
+    where
+        F: FnOnce(Vec<T>) -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        let mut cogs = self.core.cogs.lock().unwrap();
+        // Every prerequisite hands over its result, so each one has to be in the machine, once
+        let mut found = Vec::with_capacity(prerequisites.len());
+        for (index, id) in prerequisites.iter().enumerate() {
+            match cogs.get(id) {
+                Some(cog)
+                    if !cog.lock().unwrap().is_consumed()
+                        && !prerequisites[..index].contains(id) =>
+                {
+                    found.push(cog.clone())
+                }
+                _ => return Err(CogError::NotInserted(*id)),
+            }
+        }
+
+        // The prerequisites keep their results until the cog runs, so a cog that never runs
+        // leaves them to be retrieved as usual
+        let inputs = found.clone();
+        let ids = prerequisites.to_vec();
+        let core = Arc::downgrade(&self.core);
+        let func = move || {
+            let results = inputs
+                .iter()
+                .map(|prerequisite| {
+                    prerequisite
+                        .lock()
+                        .unwrap()
+                        .take_result()
+                        .expect("The cog only runs once every prerequisite has succeeded")
+                })
+                .collect();
+            // The prerequisites have handed over their results, so nothing is left of them
+            if let Some(core) = core.upgrade() {
+                let mut cogs = core.cogs.lock().unwrap();
+                for id in &ids {
+                    cogs.remove(id);
+                }
+            }
+            func(results)
+        };
+
+        let id = self.core.cog_id.fetch_add(1, Ordering::Relaxed);
+        let cog: ArcMutexCog<T> = Arc::new(Mutex::new(Cog::blocked(
+            id,
+            Box::new(func),
+            self.core.track(CogOptions::default()),
+        )));
+        // Claimed before the map is unlocked, so no other cog can consume the same results
+        for prerequisite in &found {
+            prerequisite.lock().unwrap().consumer = Some(Arc::downgrade(&cog));
+        }
+        cogs.insert(id, cog.clone());
+        drop(cogs);
+
+        self.core.wait_on_prerequisites(&cog, &found);
+        Ok(id)
+    }
+
+    /// Insert several cogs into the machine at once
+    ///
+    /// Every cog gets its own ID, and the cogs are spread over the engines.
+    /// Returns the IDs in the same order as `funcs`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    ///
+    /// let machine = Machine::powered(4);
+    /// let ids = machine.insert_cog_batch((0..8).map(|i| move || i * i).collect());
+    ///
+    /// let squares: Vec<_> = ids
+    ///     .into_iter()
+    ///     .map(|id| machine.wait_for_result(id).unwrap())
+    ///     .collect();
+    /// assert_eq!(squares, vec![0, 1, 4, 9, 16, 25, 36, 49]);
+    /// ```
+    pub fn insert_cog_batch<F>(&self, funcs: Vec<F>) -> Vec<CogId>
+    where
+        F: FnOnce() -> T + Send + std::panic::UnwindSafe + 'static,
+    {
+        let first_id = self.core.cog_id.fetch_add(funcs.len(), Ordering::Relaxed);
+        let cog_batch: Vec<ArcMutexCog<T>> = funcs
+            .into_iter()
+            .enumerate()
+            .map(|(index, func)| {
+                let func: CogFn<T> = Box::new(func);
+                Arc::new(Mutex::new(Cog::new(
+                    first_id + index,
+                    func,
+                    self.core.track(CogOptions::default()),
+                )))
+            })
+            .collect();
+
+        let ids = (first_id..first_id + cog_batch.len()).collect();
+        self.core
+            .cogs
+            .lock()
+            .unwrap()
+            .extend((first_id..).zip(cog_batch.iter().cloned()));
+        self.core.distribute_cog_batch(first_id, cog_batch);
+        ids
+    }
+
+    /// Retrieves the result of a cog (task) by its ID, removing the cog once the result is
+    /// retrieved.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The cog has not been added to the machine (`CogError::NotFound`).
+    /// - The cog has already been retrieved (`CogError::NotFound`).
+    /// - The cog has not completed (`CogError::NotCompleted`).
+    /// - The cog panicked (`CogError::Panicked`).
+    /// - The cog was cancelled (`CogError::Cancelled`).
+    ///
+    /// # Example
+    /// NOTE: The example uses wait_for_result() to retrieve the result of the cog.
+    /// This is to keep the program running synchronously
+    ///
+    /// ```
+    /// use rustycog::Machine;
+    /// use rustycog::error::CogError;
+    ///
+    /// let machine = Machine::powered(4);
+    /// let id = machine.insert_cog(|| 42);
+    ///
+    /// // First retrieval - succeeds
+    /// assert_eq!(machine.wait_for_result(id), Ok(42));
+    ///
+    /// // Second retrieval - cog is already removed
+    /// assert_eq!(machine.wait_for_result(id), Err(CogError::NotInserted(id)));
+    pub fn get_result(&self, id: CogId) -> Result<T, CogError> {
+        self.core.get_result(id)
+    }
+
+    /// Waits for the result of a cog (task) by its ID, removing the cog once the result is
+    /// retrieved.
+    ///
+    /// If the cog is still waiting to be engaged, it is run on the calling thread
+    /// instead of waiting for an engine to reach it.
+    /// When called from inside a cog, the engine keeps running other queued cogs until the
+    /// cog has finished, so cogs waiting on cogs can not deadlock the machine.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The cog has not been added to the machine (`CogError::NotFound`).
+    /// - The cog panicked (`CogError::Panicked`).
+    /// - The cog was cancelled (`CogError::Cancelled`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    /// use rustycog::error::CogError;
+    ///
+    /// let machine = Machine::powered(4);
+    ///
+    /// let cog1_id = machine.insert_cog(|| {0});
+    /// let cog2_id = machine.insert_cog(|| {
+    ///     panic!("I paniced :(");
+    ///     0
+    /// });
+    ///
+    /// assert_eq!(machine.wait_for_result(cog1_id), Ok(0));
+    /// assert!(matches!(
+    ///     machine.wait_for_result(cog2_id),
+    ///     Err(CogError::Panicked(id, _)) if id == cog2_id
+    /// ));
+    /// // Second retrieval - cog is already removed
+    /// assert_eq!(machine.wait_for_result(cog2_id), Err(CogError::NotInserted(cog2_id)));
+    ///
+    /// // No engine will ever reach this cog, so the waiting thread runs it
+    /// let cold_machine = Machine::cold(1);
+    /// let cog3_id = cold_machine.insert_cog(|| {2});
+    /// assert_eq!(cold_machine.wait_for_result(cog3_id), Ok(2));
+    /// ```
+    pub fn wait_for_result(&self, id: CogId) -> Result<T, CogError> {
+        self.core.wait_for_cog(&self.core.cog(id)?);
+        self.get_result(id)
+    }
+
+    /// Waits for the results of several cogs (tasks), removing the cogs once the results are
+    /// retrieved.
+    ///
+    /// The results are returned in the same order as `ids`. Instead of waking up for every
+    /// cog, the calling thread waits for a single signal once all of them have finished.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::powered(4);
+    /// let mut ids = machine.insert_cog_batch((0..4).map(|i| move || i * 10).collect());
+    /// ids.push(machine.insert_cog(|| panic!("Corrupt input")));
+    ///
+    /// let results = machine.wait_all(&ids);
+    /// assert_eq!(results[..4], [Ok(0), Ok(10), Ok(20), Ok(30)]);
+    /// assert!(matches!(results[4], Err(CogError::Panicked(id, _)) if id == ids[4]));
+    /// ```
+    pub fn wait_all(&self, ids: &[CogId]) -> Vec<Result<T, CogError>> {
+        let cogs: Vec<_> = ids
+            .iter()
+            .filter_map(|&id| self.core.cog(id).ok())
+            .collect();
+        if let Some(watch) = self.core.watch_all(&cogs) {
+            watch.wait_for(cogs.len());
+        }
+        ids.iter().map(|&id| self.get_result(id)).collect()
+    }
+
+    /// Waits for the results of several cogs (tasks), stopping at the first cog that fails
+    ///
+    /// If every cog succeeds, their results are returned in the same order as `ids` and the
+    /// cogs are removed. Otherwise the error of the first cog to fail is returned as soon as it
+    /// fails, and the other cogs are left in the machine.
+    /// No result is retrieved unless all of them are, so a result retrieved through the machine
+    /// by another thread in the meantime leaves the others in the machine as well.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - A cog has not been added to the machine, or is listed more than once
+    ///   (`CogError::NotInserted`).
+    /// - A cog failed, see `Machine::get_result`.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::powered(4);
+    /// let ids = machine.insert_cog_batch((1..=4).map(|i| move || i * i).collect());
+    /// assert_eq!(machine.try_collect(&ids), Ok(vec![1, 4, 9, 16]));
+    ///
+    /// let slow_id = machine.insert_cog(|| {
+    ///     std::thread::sleep(std::time::Duration::from_millis(100));
+    ///     0
+    /// });
+    /// let broken_id = machine.insert_cog(|| panic!("Corrupt input"));
+    /// assert!(matches!(
+    ///     machine.try_collect(&[slow_id, broken_id]),
+    ///     Err(CogError::Panicked(id, _)) if id == broken_id
+    /// ));
+    /// assert_eq!(machine.wait_for_result(slow_id), Ok(0));
+    ///
+    /// let id = machine.insert_cog(|| 7);
+    /// assert_eq!(machine.try_collect(&[id, id]), Err(CogError::NotInserted(id)));
+    /// assert_eq!(machine.wait_for_result(id), Ok(7));
+    /// ```
+    pub fn try_collect(&self, ids: &[CogId]) -> Result<Vec<T>, CogError> {
+        let cogs = ids
+            .iter()
+            .enumerate()
+            .map(|(index, &id)| match ids[..index].contains(&id) {
+                // Every result is taken out of the machine, so each cog can only be listed once
+                true => Err(CogError::NotInserted(id)),
+                false => self.core.cog(id),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let failed = |cog: &ArcMutexCog<T>| {
+            let cog = cog.lock().unwrap();
+            cog.is_failed().then_some(cog.id)
+        };
+        let error = |id| match self.get_result(id) {
+            Err(err) => err,
+            Ok(_) => unreachable!("The cog has failed"),
+        };
+
+        match self.core.watch_all(&cogs) {
+            Some(watch) => {
+                let failed_id = (0..cogs.len())
+                    .find_map(|_| self.core.cog(watch.next()).ok().as_ref().and_then(failed));
+                if let Some(id) = failed_id {
+                    // The cogs that have not finished yet would otherwise keep the watch
+                    self.core.unwatch_each(&cogs, &watch);
+                    return Err(error(id));
+                }
+            }
+            // The cogs have been waited on one by one already
+            None => {
+                if let Some(id) = cogs.iter().find_map(failed) {
+                    return Err(error(id));
+                }
+            }
+        }
+        self.core.take_results(ids)
+    }
+
+    /// Waits for whichever of several cogs (tasks) finishes first, removing that cog once its
+    /// result is retrieved.
+    ///
+    /// The other cogs are left in the machine. If several cogs have already finished,
+    /// the first one in `ids` is returned.
+    ///
+    /// # Errors
+    /// The result is an error if:
+    /// - A cog has not been added to the machine (`CogError::NotInserted`), which is returned
+    ///   right away.
+    /// - The cog that finished first failed, see `Machine::get_result`.
+    ///
+    /// # Panics
+    /// Panics if `ids` is empty.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::powered(2);
+    /// let slow_id = machine.insert_cog(|| {
+    ///     std::thread::sleep(Duration::from_millis(200));
+    ///     "slow mirror"
+    /// });
+    /// let fast_id = machine.insert_cog(|| "fast mirror");
+    ///
+    /// assert_eq!(machine.wait_any(&[slow_id, fast_id]), (fast_id, Ok("fast mirror")));
+    /// assert_eq!(machine.wait_any(&[slow_id]), (slow_id, Ok("slow mirror")));
+    ///
+    /// // No engine will ever reach these cogs, so the waiting thread runs one of them
+    /// let cold_machine = Machine::cold(1);
+    /// let ids = cold_machine.insert_cog_batch(vec![|| "first", || "second"]);
+    /// assert_eq!(cold_machine.wait_any(&ids), (ids[0], Ok("first")));
+    /// ```
+    pub fn wait_any(&self, ids: &[CogId]) -> (CogId, Result<T, CogError>) {
+        assert!(
+            !ids.is_empty(),
+            "wait_any needs at least one cog to wait on"
+        );
+        let mut cogs = Vec::with_capacity(ids.len());
+        for &id in ids {
+            match self.core.cog(id) {
+                Ok(cog) => cogs.push(cog),
+                Err(err) => return (id, Err(err)),
+            }
+        }
+        let watch = self.core.watch_each(&cogs);
+        let id = self.core.next_finished(&watch, &cogs);
+        // The other cogs would otherwise keep the watch until they finish
+        self.core.unwatch_each(&cogs, &watch);
+        (id, self.get_result(id))
+    }
+
+    /// Iterate over the results of the cogs (tasks) in the machine as they finish
+    ///
+    /// Every cog in the machine at the time of the call is yielded once, in the order the cogs
+    /// finish, and removed from the machine. Cogs that have already finished come first,
+    /// in insertion order. Cogs inserted later are not included.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::powered(4);
+    /// for delay in [300, 0, 150] {
+    ///     machine.insert_cog(move || {
+    ///         std::thread::sleep(Duration::from_millis(delay));
+    ///         delay
+    ///     });
+    /// }
+    ///
+    /// let delays: Vec<_> = machine
+    ///     .completions()
+    ///     .map(|(_id, result)| result.unwrap())
+    ///     .collect();
+    /// assert_eq!(delays, vec![0, 150, 300]);
+    /// ```
+    pub fn completions(&self) -> Completions<'_, T> {
+        let mut cogs: Vec<_> = self
+            .core
+            .cogs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|cog| !cog.lock().unwrap().is_consumed())
+            .cloned()
+            .collect();
+        cogs.sort_by_key(|cog| cog.lock().unwrap().id);
+        Completions::new(&self.core, cogs)
+    }
+
+    /// Waits for the result of a cog (task) for at most `timeout`, removing the cog once the
+    /// result is retrieved.
+    ///
+    /// If the cog does not finish in time, it is left in the machine and its result can be
+    /// retrieved later. Unlike `Machine::wait_for_result`, the calling thread never runs the
+    /// cog itself, so the wait can not take longer than `timeout`.
+    ///
+    /// # Notes
+    /// - When called from inside a cog, the engine keeps running queued cogs while it waits,
+    ///   including the awaited cog, like `Machine::wait_for_result` does. A cog it has started
+    ///   is finished first, so the wait may take longer than `timeout`.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The cog has not been added to the machine (`CogError::NotInserted`).
+    /// - The cog did not finish within `timeout` (`CogError::TimedOut`).
+    /// - The cog panicked (`CogError::Panicked`).
+    /// - The cog was cancelled (`CogError::Cancelled`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::powered(1);
+    /// let id = machine.insert_cog(|| {
+    ///     std::thread::sleep(Duration::from_millis(200));
+    ///     42
+    /// });
+    ///
+    /// assert_eq!(
+    ///     machine.wait_for_result_timeout(id, Duration::from_millis(10)),
+    ///     Err(CogError::TimedOut(id))
+    /// );
+    /// assert_eq!(machine.wait_for_result_timeout(id, Duration::from_secs(10)), Ok(42));
+    /// ```
+    ///
+    /// A timeout too long to compute a deadline for waits until the cog has finished:
+    /// ```
+    /// use rustycog::Machine;
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::powered(1);
+    /// let id = machine.insert_cog(|| 42);
+    ///
+    /// assert_eq!(machine.wait_for_result_timeout(id, Duration::MAX), Ok(42));
+    /// ```
+    ///
+    /// A cog waiting on a child cog does not keep its engine from running the child:
+    /// ```
+    /// use rustycog::Machine;
+    /// use std::{sync::Arc, time::Duration};
+    ///
+    /// let machine = Arc::new(Machine::powered(1));
+    /// let shared = machine.clone();
+    /// let id = machine.insert_cog(move || {
+    ///     let child_id = shared.insert_cog(|| 21);
+    ///     shared.wait_for_result_timeout(child_id, Duration::from_secs(10)).unwrap() * 2
+    /// });
+    ///
+    /// assert_eq!(machine.wait_for_result_timeout(id, Duration::from_secs(10)), Ok(42));
+    /// ```
+    pub fn wait_for_result_timeout(&self, id: CogId, timeout: Duration) -> Result<T, CogError> {
+        // A timeout too long to keep track of leaves the wait without a deadline
+        self.wait_for_result_until(id, Instant::now().checked_add(timeout))
+    }
+
+    /// Waits for the result of a cog (task) until `deadline`, removing the cog once the
+    /// result is retrieved.
+    ///
+    /// Works like `Machine::wait_for_result_timeout`, but with a point in time instead of
+    /// a duration, which is convenient when several waits share the same deadline.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The cog has not been added to the machine (`CogError::NotInserted`).
+    /// - The cog did not finish before `deadline` (`CogError::TimedOut`).
+    /// - The cog panicked (`CogError::Panicked`).
+    /// - The cog was cancelled (`CogError::Cancelled`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let machine = Machine::powered(4);
+    /// let ids: Vec<_> = (0..4).map(|i| machine.insert_cog(move || i)).collect();
+    ///
+    /// let deadline = Instant::now() + Duration::from_secs(10);
+    /// for (i, id) in ids.into_iter().enumerate() {
+    ///     assert_eq!(machine.wait_for_result_deadline(id, deadline), Ok(i));
+    /// }
+    /// ```
+    pub fn wait_for_result_deadline(&self, id: CogId, deadline: Instant) -> Result<T, CogError> {
+        self.wait_for_result_until(id, Some(deadline))
+    }
+
+    /// Waits for the result of a cog without running it, giving up once `deadline` has passed
+    fn wait_for_result_until(&self, id: CogId, deadline: Option<Instant>) -> Result<T, CogError> {
+        let cog = self.core.cog(id)?;
+        // The engine blocking here could be the one that has to run the cog
+        let finished = match self.core.on_engine() {
+            true => self.core.help_until_done(&cog, deadline),
+            false => Cog::wait(&cog, deadline),
+        };
+        if !finished {
+            return Err(CogError::TimedOut(id));
+        }
+        self.get_result(id)
+    }
+
+    /// Cancels a cog (task) that has not started running yet
+    ///
+    /// The cancelled cog is never run, even if an engine already has it queued.
+    /// Retrieving its result returns `CogError::Cancelled`.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The cog has not been added to the machine (`CogError::NotInserted`).
+    /// - The cog is already running or has finished (`CogError::AlreadyEngaged`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::cold(1);
+    /// let id = machine.insert_cog(|| 42);
+    ///
+    /// assert_eq!(machine.cancel_cog(id), Ok(()));
+    /// assert_eq!(machine.wait_for_result(id), Err(CogError::Cancelled(id)));
+    /// assert_eq!(machine.cancel_cog(id), Err(CogError::NotInserted(id)));
+    /// ```
+    pub fn cancel_cog(&self, id: CogId) -> Result<(), CogError> {
+        Cog::cancel(&*self.core.cog(id)?)
+    }
+
+    /// Requests cancellation of a cog (task), even if it is already running
+    ///
+    /// Waiting cogs are cancelled right away, like with `Machine::cancel_cog`.
+    /// Running cogs have their `CancelToken` flipped and resolve to `CogError::Cancelled`
+    /// once they return. Waiters are woken up as soon as the cog stops.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The cog has not been added to the machine (`CogError::NotInserted`).
+    /// - The cog has already finished (`CogError::AlreadyEngaged`).
+    /// - The cog is running, but was not inserted with a `CancelToken`
+    ///   (`CogError::AlreadyEngaged`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::cold(1);
+    /// let id = machine.insert_cog_with_token(|_token| 42);
+    ///
+    /// assert_eq!(machine.request_cancel(id), Ok(()));
+    /// assert_eq!(machine.wait_for_result(id), Err(CogError::Cancelled(id)));
+    /// ```
+    ///
+    /// A running cog without a token can not be asked to stop, and keeps its result:
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    ///
+    /// let machine = Machine::powered(1);
+    /// let (started_tx, started_rx) = std::sync::mpsc::channel();
+    /// let (finish_tx, finish_rx) = std::sync::mpsc::channel::<()>();
+    /// let id = machine.insert_cog(move || {
+    ///     started_tx.send(()).unwrap();
+    ///     finish_rx.recv().unwrap();
+    ///     42
+    /// });
+    ///
+    /// started_rx.recv().unwrap();
+    /// assert_eq!(machine.request_cancel(id), Err(CogError::AlreadyEngaged(id)));
+    /// finish_tx.send(()).unwrap();
+    /// assert_eq!(machine.wait_for_result(id), Ok(42));
+    /// ```
+    pub fn request_cancel(&self, id: CogId) -> Result<(), CogError> {
+        Cog::request_cancel(&*self.core.cog(id)?)
+    }
+
+    /// Wait for the machine (task manager) to finish
+    ///
+    /// Pause execution until the machine has finished running
+    /// all of its cogs (tasks), including cogs inserted while waiting.
+    /// Cogs held back by prerequisites count as well, until they have either run or failed.
+    ///
+    /// # Notes
+    /// - Calling this from inside a cog never returns, since the cog itself is not finished.
+    /// - A cold machine does not run its cogs, so this only returns once it is powered.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError};
+    /// let machine = Machine::powered(4);
+    ///
+    /// for i in 0..1000 {
+    ///     machine.insert_cog(move || i);
+    /// }
+    ///
+    /// let result = 111111;
+    ///
+    /// let last_id = machine.insert_cog(move || {
+    ///     std::thread::sleep(std::time::Duration::from_secs(1));
+    ///     result
+    /// });
+    ///
+    /// // Wait for all tasks
+    /// assert_eq!(machine.get_result(last_id), Err(CogError::NotCompleted(last_id)));
+    /// machine.wait_until_done();
+    /// assert_eq!(machine.get_result(last_id), Ok(result));
+    /// ```
+    ///
+    /// Cogs inserted into a cold machine are waited for once it is powered:
+    /// ```
+    /// use rustycog::Machine;
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::cold(2);
+    /// let id = machine.insert_cog(|| 42);
+    ///
+    /// machine.power().unwrap();
+    /// assert_eq!(machine.wait_until_done_timeout(Duration::from_secs(10)), Ok(()));
+    /// assert_eq!(machine.get_result(id), Ok(42));
+    /// ```
+    pub fn wait_until_done(&self) {
+        self.core.outstanding.wait(None);
+    }
+
+    /// Wait for the machine (task manager) to finish, for at most `timeout`
+    ///
+    /// Works like `Machine::wait_until_done`, but gives up once `timeout` has passed.
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - Cogs are still unfinished after `timeout` (`MachineError::TimedOut`).
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::MachineError};
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::powered(1);
+    /// machine.insert_cog(|| std::thread::sleep(Duration::from_millis(200)));
+    ///
+    /// assert_eq!(
+    ///     machine.wait_until_done_timeout(Duration::from_millis(10)),
+    ///     Err(MachineError::TimedOut)
+    /// );
+    /// assert_eq!(machine.wait_until_done_timeout(Duration::from_secs(10)), Ok(()));
+    /// ```
+    pub fn wait_until_done_timeout(&self, timeout: Duration) -> Result<(), MachineError> {
+        let deadline = Instant::now().checked_add(timeout);
+        if self.core.outstanding.wait(deadline) {
+            Ok(())
+        } else {
+            Err(MachineError::TimedOut)
+        }
+    }
+
+    /// Shut down the machine (task manager)
+    ///
+    /// Stops all engines, after dealing with the cogs (tasks) that have not run yet
+    /// according to `mode`:
+    /// - `ShutdownMode::Drain` runs them first, like `Machine::wait_until_done`.
+    ///   The cogs of a cold machine are run on the calling thread.
+    /// - `ShutdownMode::Abort` fails them with `CogError::Shutdown`, so everyone waiting for
+    ///   them returns right away. Cogs that are already running are allowed to finish.
+    ///
+    /// Dropping a machine shuts it down with `ShutdownMode::Abort`.
+    /// A paused machine is resumed when draining, so its cogs can run, and stays paused when
+    /// aborting.
+    /// Afterwards the machine is cold, and cogs inserted into it wait until it is powered again.
+    ///
+    /// # Notes
+    /// - Draining from inside a cog never returns, since the cog itself is not finished.
+    /// - When called from inside a cog, the engine running the cog is not waited for,
+    ///   and stops once the cog returns.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::{Machine, error::CogError, types::ShutdownMode};
+    ///
+    /// let machine = Machine::cold(4);
+    /// let ids: Vec<_> = (0..3).map(|i| machine.insert_cog(move || i)).collect();
+    ///
+    /// let report = machine.shutdown(ShutdownMode::Abort);
+    /// assert_eq!(report.dropped, ids);
+    /// assert_eq!(machine.get_result(ids[0]), Err(CogError::Shutdown(ids[0])));
+    /// assert_eq!(machine.engine_count(), 0);
+    /// ```
+    ///
+    /// Draining runs every cog before stopping the engines:
+    /// ```
+    /// use rustycog::{Machine, types::ShutdownMode};
+    /// use std::time::Duration;
+    ///
+    /// let machine = Machine::powered(2);
+    /// let ids: Vec<_> = (0..8)
+    ///     .map(|i| {
+    ///         machine.insert_cog(move || {
+    ///             std::thread::sleep(Duration::from_millis(10));
+    ///             i
+    ///         })
+    ///     })
+    ///     .collect();
+    ///
+    /// let report = machine.shutdown(ShutdownMode::Drain);
+    /// assert!(report.dropped.is_empty());
+    /// assert_eq!(machine.try_collect(&ids), Ok((0..8).collect()));
+    /// ```
+    pub fn shutdown(&self, mode: ShutdownMode) -> ShutdownReport {
+        if mode == ShutdownMode::Drain {
+            self.core.paused.store(false, Ordering::SeqCst);
+            self.core.notify_work();
+            self.core.drain();
+        }
+        // No more engines may be spawned for the cogs inserted from here on
+        self.core.powered.store(false, Ordering::SeqCst);
+        let dropped = match mode {
+            ShutdownMode::Drain => Vec::new(),
+            ShutdownMode::Abort => self.core.abort(),
+        };
+        // Take the engines out first, so idle engines retiring themselves can not block the kill
+        let engines = std::mem::take(&mut *self.core.engines.write().unwrap());
+        let current = engine::current();
+        for engine in engines {
+            // An engine can not wait for its own thread, so it is left to stop by itself
+            if current == Some((self.core.id, engine.read().unwrap().id)) {
+                drop(Engine::stop(&engine));
+            } else {
+                Engine::kill(&engine);
+            }
+        }
+        ShutdownReport { dropped }
+    }
+}
+
+impl<T: CogType> Core<T> {
+    pub fn spawn_engines(&self, amount: u32) {
+        let retirement = self.keep_alive.map(|keep_alive| Retirement {
+            min_engines: self.min_engines as usize,
+            keep_alive,
+        });
+        let mut engines = self.engines.write().unwrap();
+        // Other threads may be scaling up at the same time
+        let amount = usize::min(
+            amount as usize,
+            (self.max_engines as usize).saturating_sub(engines.len()),
+        );
+        for _ in 0..amount {
+            engines.push(Engine::new(
+                self.engine_id.fetch_add(1, Ordering::Relaxed),
+                self.id,
+                self.engines.clone(),
+                self.work.clone(),
+                self.paused.clone(),
+                retirement,
+            ));
+        }
+    }
+
+    /// Spawns another engine if there are none left, or if cogs are queueing up
+    pub fn scale_engines(&self) {
+        if !self.powered.load(Ordering::SeqCst) {
+            return;
+        }
+        let backed_up = {
+            let engines = self.engines.read().unwrap();
+            if engines.len() >= self.max_engines as usize {
+                return;
+            }
+            let queued: usize = engines
+                .iter()
+                .map(|engine| engine.read().unwrap().local_queue.read().unwrap().len())
+                .sum();
+            engines.is_empty() || queued > engines.len()
+        };
+        if backed_up {
+            self.spawn_engines(1);
+        }
+    }
+
+    pub fn insert_boxed_cog(&self, func: CogFn<T>, options: CogOptions) -> CogId {
+        let id = self.cog_id.fetch_add(1, Ordering::Relaxed);
+        let priority = options.priority;
+        let cog: ArcMutexCog<T> = Arc::new(Mutex::new(Cog::new(id, func, self.track(options))));
+        self.cogs.lock().unwrap().insert(id, cog.clone());
+        self.distribute_cog(cog, priority);
+        id
+    }
+
+    pub fn insert_dependent_cog(
+        &self,
+        func: CogFn<T>,
+        prerequisites: Vec<ArcMutexCog<T>>,
+    ) -> CogId {
+        let id = self.cog_id.fetch_add(1, Ordering::Relaxed);
+        let cog: ArcMutexCog<T> = Arc::new(Mutex::new(Cog::blocked(
+            id,
+            func,
+            self.track(CogOptions::default()),
+        )));
+        self.cogs.lock().unwrap().insert(id, cog.clone());
+        self.wait_on_prerequisites(&cog, &prerequisites);
+        id
+    }
+
+    /// Wires a blocked cog to its prerequisites, releasing it if they have all succeeded
+    /// or failing it if one of them has failed
+    pub fn wait_on_prerequisites(&self, cog: &ArcMutexCog<T>, prerequisites: &[ArcMutexCog<T>]) {
+        let mut failed = None;
+        for prerequisite in prerequisites {
+            if let Err(id) = Cog::add_dependent(prerequisite, cog) {
+                failed = failed.or(Some(id));
+            }
+        }
+
+        let mut ready = Vec::new();
+        Cog::resolve_prerequisite(cog, failed, &mut ready);
+        self.distribute_released(ready);
+    }
+
+    pub fn distribute_cog(&self, cog: ArcMutexCog<T>, priority: Priority) {
+        let cog_id = cog.lock().unwrap().id;
+        self.scale_engines();
+        // The engine list stays locked until the cog is queued, so the engine can not retire
+        let engines = self.engines.read().unwrap();
+        if !engines.is_empty() {
+            let engine = engines[cog_id % engines.len()].read().unwrap();
+            engine.local_queue.write().unwrap().push_back(cog, priority);
+
+            self.notify_work();
+        }
+    }
+
+    /// Spreads a batch of cogs with consecutive IDs, starting at `first_id`, over the engines
+    /// the same way `Core::distribute_cog` would, locking each queue only once
+    pub fn distribute_cog_batch(&self, first_id: CogId, cogs: Vec<ArcMutexCog<T>>) {
+        if cogs.is_empty() {
+            return;
+        }
+        self.scale_engines();
+        let engines = self.engines.read().unwrap();
+        if engines.is_empty() {
+            return;
+        }
+
+        let mut shares: Vec<Vec<ArcMutexCog<T>>> = engines.iter().map(|_| Vec::new()).collect();
+        for (index, cog) in cogs.into_iter().enumerate() {
+            shares[(first_id + index) % engines.len()].push(cog);
+        }
+        for (engine, share) in engines.iter().zip(shares) {
+            if !share.is_empty() {
+                let engine = engine.read().unwrap();
+                engine
+                    .local_queue
+                    .write()
+                    .unwrap()
+                    .extend(share, Priority::Normal);
+            }
+        }
+        self.notify_work();
+    }
+
+    pub fn distribute_released(&self, released: Vec<ArcMutexCog<T>>) {
+        for cog in released {
+            let priority = cog.lock().unwrap().priority;
+            self.distribute_cog(cog, priority);
+        }
+    }
+
+    pub fn notify_work(&self) {
+        let (lock, cvar) = &*self.work;
+        let mut work = lock.lock().unwrap();
+        *work = true;
+        cvar.notify_all();
+    }
+
+    pub fn cog(&self, id: CogId) -> Result<ArcMutexCog<T>, CogError> {
+        self.cogs
+            .lock()
+            .unwrap()
+            .get(&id)
+            .filter(|cog| !cog.lock().unwrap().is_consumed())
+            .cloned()
+            .ok_or(CogError::NotInserted(id))
+    }
+
+    pub fn get_result(&self, id: CogId) -> Result<T, CogError> {
+        Self::take_result(&mut self.cogs.lock().unwrap(), id)
+    }
+
+    /// Retrieves the results of several cogs, but only if every one of them has succeeded.
+    /// Otherwise the results are left in the machine, and retrieving the first cog that has not
+    /// succeeded returns its error.
+    /// Handles retrieve results without locking the machine, so one of them may still take a
+    /// result in between, in which case its error is returned instead.
+    pub fn take_results(&self, ids: &[CogId]) -> Result<Vec<T>, CogError> {
+        let mut cogs = self.cogs.lock().unwrap();
+        for &id in ids {
+            let cog = cogs.get(&id).cloned().ok_or(CogError::NotInserted(id))?;
+            let mut locked_cog = cog.lock().unwrap();
+            if locked_cog.is_done() && !locked_cog.is_consumed() {
+                continue;
+            }
+            // The cog stays locked, so this can only retrieve an error
+            let (result, finished) = locked_cog.retrieve();
+            drop(locked_cog);
+            if finished {
+                cogs.remove(&id);
+            }
+            return result.and(Err(CogError::NotCompleted(id)));
+        }
+        ids.iter()
+            .map(|&id| Self::take_result(&mut cogs, id))
+            .collect()
+    }
+
+    fn take_result(cogs: &mut HashMap<CogId, ArcMutexCog<T>>, id: CogId) -> Result<T, CogError> {
+        let (result, finished) = match cogs.get(&id) {
+            Some(cog) => cog.lock().unwrap().retrieve(),
+            None => (Err(CogError::NotInserted(id)), false),
+        };
+        if finished {
+            cogs.remove(&id);
+        }
+        result
+    }
+
+    /// Runs every cog that has not finished yet, returning once none are left
+    fn drain(&self) {
+        // A cold machine has no engines to run its cogs, so the calling thread runs them
+        while self.engines.read().unwrap().is_empty() {
+            for cog in self.waiting_cogs() {
+                // Dependents released by the cog become waiting, and are run on the next round
+                let _ = Cog::run(&cog);
+            }
+            if self.outstanding.wait(Some(Instant::now() + HELP_INTERVAL)) {
+                return;
+            }
+        }
+        self.outstanding.wait(None);
+    }
+
+    /// The cogs that are waiting to be engaged, in insertion order.
+    /// Cogs stay in the machine until they have finished, even when their results are
+    /// consumed by other cogs, so this covers every cog that still has to run.
+    fn waiting_cogs(&self) -> Vec<ArcMutexCog<T>> {
+        let mut waiting: Vec<_> = self
+            .cogs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|cog| cog.lock().unwrap().is_waiting())
+            .cloned()
+            .collect();
+        waiting.sort_by_key(|cog| cog.lock().unwrap().id);
+        waiting
+    }
+
+    /// Fails every cog that has not been engaged yet with `CogError::Shutdown`.
+    /// Returns their IDs, in insertion order.
+    fn abort(&self) -> Vec<CogId> {
+        let cogs: Vec<_> = self.cogs.lock().unwrap().values().cloned().collect();
+        let mut dropped: Vec<_> = cogs.iter().filter_map(|cog| Cog::shut_down(cog)).collect();
+        dropped.sort_unstable();
+        dropped
+    }
+
+    /// Counts the cog about to be created with `options` as outstanding, until it finishes
+    fn track(&self, options: CogOptions) -> CogOptions {
+        self.outstanding.add(1);
+        CogOptions {
+            outstanding: Some(self.outstanding.clone()),
+            ..options
+        }
+    }
+
+    /// Inserts a cog, queueing it on the engine running on the current thread if there is one
+    pub fn insert_local_cog(&self, func: CogFn<T>) -> ArcMutexCog<T> {
+        let id = self.cog_id.fetch_add(1, Ordering::Relaxed);
+        let cog: ArcMutexCog<T> = Arc::new(Mutex::new(Cog::new(
+            id,
+            func,
+            self.track(CogOptions::default()),
+        )));
+        self.cogs.lock().unwrap().insert(id, cog.clone());
+
+        match self.local_queue() {
+            Some(local_queue) => {
+                self.scale_engines();
+                local_queue
+                    .write()
+                    .unwrap()
+                    .push_back(cog.clone(), Priority::Normal);
+                self.notify_work();
+            }
+            None => self.distribute_cog(cog.clone(), Priority::Normal),
+        }
+        cog
+    }
+
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Whether the current thread is one of the engines of this machine
+    fn on_engine(&self) -> bool {
+        engine::current().is_some_and(|(machine_id, _)| machine_id == self.id)
+    }
+
+    /// The queue of the engine running on the current thread, if it belongs to this machine
+    fn local_queue(&self) -> Option<LocalQueue<T>> {
+        let (machine_id, engine_id) = engine::current()?;
+        if machine_id != self.id {
+            return None;
+        }
+        let engines = self.engines.read().unwrap();
+        let engine = engines
+            .iter()
+            .find(|engine| engine.read().unwrap().id == engine_id)?;
+        Some(engine.read().unwrap().local_queue.clone())
+    }
+
+    /// Runs two cogs, possibly in parallel, and returns both results once both have finished
+    pub fn join(&self, a: CogFn<T>, b: CogFn<T>) -> Result<(T, T), CogError> {
+        // `b` is queued first, so it is the one other engines steal
+        let b = self.insert_local_cog(b);
+        let a = self.insert_local_cog(a);
+        self.wait_for_cog(&a);
+        self.wait_for_cog(&b);
+
+        let (a, b) = (a.lock().unwrap().id, b.lock().unwrap().id);
+        let (a, b) = (self.get_result(a), self.get_result(b));
+        Ok((a?, b?))
+    }
+
+    /// Blocks until the cog has finished, or until `deadline` has passed, running other queued
+    /// cogs in the meantime, so waiting on a cog never keeps the work it depends on from being
+    /// done. A cog that is already running when `deadline` passes is finished first.
+    /// Returns true if the cog has finished.
+    pub fn help_until_done(&self, cog: &ArcMutexCog<T>, deadline: Option<Instant>) -> bool {
+        loop {
+            if !self.is_paused()
+                && let Ok(released) = Cog::run(cog)
+            {
+                self.distribute_released(released);
+            }
+            if cog.lock().unwrap().is_finished() {
+                return true;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return false;
+            }
+            match self.find_work() {
+                Some(work) => {
+                    if let Ok(released) = Cog::run(&work) {
+                        self.distribute_released(released);
+                    }
+                }
+                None => {
+                    let interval = Instant::now() + HELP_INTERVAL;
+                    Cog::wait(
+                        cog,
+                        Some(deadline.map_or(interval, |deadline| deadline.min(interval))),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Takes a queued cog, preferring the queue of the engine running on the current thread
+    fn find_work(&self) -> Option<ArcMutexCog<T>> {
+        if self.is_paused() {
+            return None;
+        }
+        let local = self
+            .local_queue()
+            .and_then(|local_queue| local_queue.write().unwrap().pop_front());
+        local.or_else(|| Engine::steal_one(&self.engines))
+    }
+
+    /// Subscribes a single watch to all of the cogs.
+    /// Engines have to keep working while they wait, and without engines the cogs are only
+    /// run by waiting on them, so in those cases the cogs are waited on one by one instead
+    /// and `None` is returned once all of them have finished.
+    pub fn watch_all(&self, cogs: &[ArcMutexCog<T>]) -> Option<Arc<Watch>> {
+        if self.on_engine() || self.engines.read().unwrap().is_empty() {
+            for cog in cogs {
+                self.wait_for_cog(cog);
+            }
+            return None;
+        }
+        Some(self.watch_each(cogs))
+    }
+
+    /// Subscribes a single watch to every one of the cogs
+    pub fn watch_each(&self, cogs: &[ArcMutexCog<T>]) -> Arc<Watch> {
+        let watch = Arc::new(Watch::new());
+        for cog in cogs {
+            Cog::watch(cog, &watch);
+        }
+        watch
+    }
+
+    /// Unsubscribes `watch` from every one of the cogs
+    pub fn unwatch_each(&self, cogs: &[ArcMutexCog<T>], watch: &Arc<Watch>) {
+        for cog in cogs {
+            Cog::unwatch(cog, watch);
+        }
+    }
+
+    /// Blocks until the next of the cogs subscribed to `watch` has finished, returning its ID
+    pub fn next_finished(&self, watch: &Watch, cogs: &[ArcMutexCog<T>]) -> CogId {
+        let on_engine = self.on_engine();
+        loop {
+            if let Some(id) = watch.next_before(Some(Instant::now())) {
+                return id;
+            }
+            // Engines have to keep working while they wait,
+            // and without engines the cogs are only run by the waiting thread
+            if !on_engine && !self.engines.read().unwrap().is_empty() {
+                return watch.next();
+            }
+            let work = match self.is_paused() {
+                true => None,
+                // Prefer running one of the awaited cogs over helping with other work
+                false => cogs
+                    .iter()
+                    .find(|cog| cog.lock().unwrap().is_waiting())
+                    .cloned()
+                    .or_else(|| on_engine.then(|| self.find_work()).flatten())
+                    // Without engines, blocked cogs are only released by running what they wait on
+                    .or_else(|| {
+                        cogs.iter()
+                            .find_map(|cog| Cog::waiting_prerequisites(cog).pop())
+                    }),
+            };
+            match work {
+                Some(work) => {
+                    if let Ok(released) = Cog::run(&work) {
+                        self.distribute_released(released);
+                    }
+                }
+                // Whatever is left is running elsewhere, or waiting on cogs that are
+                None => {
+                    if let Some(id) = watch.next_before(Some(Instant::now() + HELP_INTERVAL)) {
+                        return id;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Blocks until the cog has finished.
+    /// If no engine has picked the cog up yet, it is run right away instead of
+    /// waiting behind everything queued before it, as are its prerequisites if the machine
+    /// has no engines.
+    pub fn wait_for_cog(&self, cog: &ArcMutexCog<T>) {
+        // The engine blocking here could be the one that has to run what the cog is waiting on
+        if self.on_engine() {
+            self.help_until_done(cog, None);
+            return;
+        }
+        loop {
+            // Without engines, nothing else runs the cogs a blocked cog is waiting on
+            if self.engines.read().unwrap().is_empty() && cog.lock().unwrap().is_blocked() {
+                for prerequisite in Cog::waiting_prerequisites(cog) {
+                    self.run_unless_paused(&prerequisite);
+                }
+            }
+            self.run_unless_paused(cog);
+            // The engines run whatever the cog still needs, unless the machine is paused
+            if !self.is_paused() && !self.engines.read().unwrap().is_empty() {
+                Cog::wait(cog, None);
+                return;
+            }
+            if Cog::wait(cog, Some(Instant::now() + HELP_INTERVAL)) {
+                return;
+            }
+        }
+    }
+
+    /// Runs a waiting cog on the calling thread, unless the machine is paused
+    fn run_unless_paused(&self, cog: &ArcMutexCog<T>) {
+        if !self.is_paused()
+            && let Ok(released) = Cog::run(cog)
+        {
+            self.distribute_released(released);
         }
     }
 }
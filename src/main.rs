@@ -1,20 +1,20 @@
 use rustycog::Machine;
 
 fn main() {
-    let mut machine = Machine::<usize>::powered(8);
+    let machine = Machine::<usize>::powered(8);
 
     let cogs = 1_000_000;
 
+    let mut ids = Vec::new();
     for i in 0..100 {
         let mut cog_vec = Vec::new();
         for j in 0..(cogs / 100) {
             cog_vec.push(move || i * j)
         }
-        machine.insert_cog_batch(cog_vec);
+        ids.extend(machine.insert_cog_batch(cog_vec));
     }
 
-    for i in 0..cogs {
-        let result = machine.wait_for_result(i);
+    for result in machine.wait_all(&ids) {
         println!("Result: {:?}", result);
     }
 
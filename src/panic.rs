@@ -0,0 +1,65 @@
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    panic::UnwindSafe,
+    sync::{Arc, Once},
+};
+
+use crate::error::CogPanic;
+
+thread_local! {
+    /// How many cogs are currently running on this thread
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    /// Where the last panic inside a cog on this thread happened
+    static LAST_PANIC: RefCell<Option<(String, Backtrace)>> = const { RefCell::new(None) };
+}
+
+static HOOK: Once = Once::new();
+
+/// Wraps the current panic hook, so panics inside cogs also record their location and backtrace.
+/// Panics outside of cogs, and the output of the previous hook, are left untouched.
+fn install_hook() {
+    HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if DEPTH.try_with(Cell::get).unwrap_or(0) > 0 {
+                let location = info
+                    .location()
+                    .map(|location| location.to_string())
+                    .unwrap_or_default();
+                let _ = LAST_PANIC.try_with(|last| {
+                    *last.borrow_mut() = Some((location, Backtrace::capture()));
+                });
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Runs a cog function, turning a panic into a `CogPanic`
+pub fn catch<R>(func: impl FnOnce() -> R + UnwindSafe) -> Result<R, CogPanic> {
+    install_hook();
+
+    // A panic caught inside an earlier cog may have left its location behind, and panics
+    // resumed with `resume_unwind` do not pass through the hook to replace it
+    LAST_PANIC.with(|last| last.borrow_mut().take());
+    DEPTH.with(|depth| depth.set(depth.get() + 1));
+    let result = std::panic::catch_unwind(func);
+    DEPTH.with(|depth| depth.set(depth.get() - 1));
+
+    result.map_err(|payload| {
+        let (location, backtrace) = match LAST_PANIC.with(|last| last.borrow_mut().take()) {
+            Some((location, backtrace)) => (Some(location), Some(Arc::new(backtrace))),
+            None => (None, None),
+        };
+        CogPanic::new(message(payload.as_ref()), location, backtrace)
+    })
+}
+
+fn message(payload: &(dyn Any + Send)) -> Option<String> {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+}
@@ -0,0 +1,69 @@
+use std::collections::VecDeque;
+
+use crate::types::Priority;
+
+/// FIFO queue per priority level, always handing out the most important work first
+pub struct PriorityQueue<C> {
+    levels: [VecDeque<C>; Priority::LEVELS],
+}
+
+impl<C> Default for PriorityQueue<C> {
+    fn default() -> Self {
+        Self {
+            levels: std::array::from_fn(|_| VecDeque::new()),
+        }
+    }
+}
+
+impl<C> PriorityQueue<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_back(&mut self, item: C, priority: Priority) {
+        self.levels[priority.level()].push_back(item);
+    }
+
+    pub fn extend<I: IntoIterator<Item = C>>(&mut self, items: I, priority: Priority) {
+        self.levels[priority.level()].extend(items);
+    }
+
+    pub fn append(&mut self, other: &mut Self) {
+        for (level, other_level) in self.levels.iter_mut().zip(other.levels.iter_mut()) {
+            level.append(other_level);
+        }
+    }
+
+    pub fn pop_front(&mut self) -> Option<C> {
+        self.levels
+            .iter_mut()
+            .rev()
+            .find_map(|level| level.pop_front())
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.iter().map(VecDeque::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.iter().all(VecDeque::is_empty)
+    }
+
+    /// The priority of the most important item in the queue
+    pub fn top_priority(&self) -> Option<Priority> {
+        Priority::ALL
+            .into_iter()
+            .rev()
+            .find(|priority| !self.levels[priority.level()].is_empty())
+    }
+
+    /// Takes `1..=len / share` items from the front of the given priority level
+    pub fn steal(&mut self, priority: Priority, share: usize) -> Self {
+        let level = &mut self.levels[priority.level()];
+        let amount = usize::max(1, level.len() / usize::max(1, share)).min(level.len());
+
+        let mut stolen = Self::new();
+        stolen.extend(level.drain(0..amount), priority);
+        stolen
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    cog::{ArcMutexCog, CogFn},
+    handle::CogHandle,
+    machine::Core,
+    types::CogType,
+};
+
+/// A scope to insert cogs that borrow from the stack, created by `Machine::scope`
+///
+/// Every cog inserted through the scope has finished by the time `Machine::scope` returns,
+/// so the cogs can borrow anything that outlives the scope.
+/// Results that were not retrieved inside the scope stay in the machine.
+pub struct Scope<'scope, 'env: 'scope, T: CogType> {
+    core: Arc<Core<T>>,
+    cogs: Mutex<Vec<ArcMutexCog<T>>>,
+    // Same variance as `std::thread::Scope`
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope, 'env, T: CogType> Scope<'scope, 'env, T> {
+    pub(crate) fn new(core: Arc<Core<T>>) -> Self {
+        Self {
+            core,
+            cogs: Mutex::new(Vec::new()),
+            scope: PhantomData,
+            env: PhantomData,
+        }
+    }
+
+    /// Insert a cog that can borrow from outside the scope
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::Machine;
+    ///
+    /// let machine = Machine::powered(4);
+    /// let words = vec![String::from("scoped"), String::from("cogs")];
+    ///
+    /// let lengths: Vec<_> = machine.scope(|scope| {
+    ///     let handles: Vec<_> = words
+    ///         .iter()
+    ///         .map(|word| scope.insert_cog(move || word.len()))
+    ///         .collect();
+    ///     handles.into_iter().map(|handle| handle.wait().unwrap()).collect()
+    /// });
+    ///
+    /// assert_eq!(lengths, vec![6, 4]);
+    /// assert_eq!(words.len(), 2);
+    /// ```
+    pub fn insert_cog<F>(&'scope self, func: F) -> CogHandle<T>
+    where
+        F: FnOnce() -> T + Send + std::panic::UnwindSafe + 'scope,
+    {
+        let func: Box<dyn FnOnce() -> T + Send + std::panic::UnwindSafe + 'scope> = Box::new(func);
+        // SAFETY: The scope waits for every cog it inserted to finish before it ends,
+        // and a cog drops its function before it is marked as finished (see `Cog::settle`).
+        // So the function never outlives the data it borrows.
+        let func = unsafe {
+            std::mem::transmute::<
+                Box<dyn FnOnce() -> T + Send + std::panic::UnwindSafe + 'scope>,
+                CogFn<T>,
+            >(func)
+        };
+        let cog = self.core.insert_local_cog(func);
+        self.cogs.lock().unwrap().push(cog.clone());
+
+        let id = cog.lock().unwrap().id;
+        CogHandle::new(id, cog, &self.core)
+    }
+
+    /// Blocks until every cog inserted through the scope has finished,
+    /// including cogs inserted by other scoped cogs meanwhile
+    pub(crate) fn wait(&self) {
+        loop {
+            // Pop in its own statement, so cogs can still be inserted while waiting
+            let cog = self.cogs.lock().unwrap().pop();
+            match cog {
+                Some(cog) => self.core.wait_for_cog(&cog),
+                None => return,
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// A token a running cog (task) can poll to find out if it should stop early
+///
+/// Tokens are handed to cogs inserted with `Machine::insert_cog_with_token` and are
+/// flipped by `Machine::request_cancel`. Cancellation is cooperative: the cog decides
+/// when to check the token and return.
+///
+/// # Example
+/// ```
+/// use rustycog::{Machine, CancelToken};
+///
+/// let machine = Machine::powered(1);
+/// let id = machine.insert_cog_with_token(|token: CancelToken| {
+///     let mut iterations = 0;
+///     while !token.is_cancelled() && iterations < 1000 {
+///         iterations += 1;
+///     }
+///     iterations
+/// });
+///
+/// let _ = machine.wait_for_result(id);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true if cancellation of the cog (task) has been requested
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+}
@@ -1,5 +1,132 @@
+use std::{any::Any, time::Duration};
+
 pub type CogId = usize;
 pub type EngineId = usize;
+pub type MachineId = usize;
+/// How an `AnyMachine` stores the results of its cogs
+pub type AnyResult = Box<dyn Any + Send>;
 
 pub trait CogType: Send + 'static {}
 impl<T: Send + 'static> CogType for T {}
+
+/// The priority of a cog (task)
+///
+/// Engines always run the highest priority cogs they have queued first,
+/// and prefer stealing high priority cogs from other engines.
+/// Cogs with the same priority run in insertion order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    pub(crate) const LEVELS: usize = 3;
+    pub(crate) const ALL: [Priority; Priority::LEVELS] =
+        [Priority::Low, Priority::Normal, Priority::High];
+
+    pub(crate) fn level(self) -> usize {
+        self as usize
+    }
+}
+
+/// How often a panicking cog (task) is run again before it is considered panicked
+///
+/// # Example
+/// ```
+/// use rustycog::types::{Backoff, RetryPolicy};
+/// use std::time::Duration;
+///
+/// // Up to 3 retries, waiting 10ms, 20ms and 40ms before them
+/// let policy = RetryPolicy::new(3).with_backoff(Backoff::Exponential(Duration::from_millis(10)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// The amount of times the cog is run again after panicking
+    pub retries: u32,
+    /// How long to wait before each retry
+    pub backoff: Backoff,
+}
+
+impl RetryPolicy {
+    /// Retry up to `retries` times, without waiting in between
+    pub fn new(retries: u32) -> Self {
+        Self {
+            retries,
+            backoff: Backoff::None,
+        }
+    }
+
+    /// Wait according to `backoff` before each retry
+    pub fn with_backoff(self, backoff: Backoff) -> Self {
+        Self { backoff, ..self }
+    }
+}
+
+/// How `Machine::shutdown` deals with the cogs (tasks) that have not run yet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownMode {
+    /// Run every cog that is queued or held back by prerequisites, then stop the engines
+    Drain,
+    /// Let running cogs finish, but fail every other cog with `CogError::Shutdown`
+    Abort,
+}
+
+/// The cogs (tasks) a `Machine::shutdown` dropped without running them
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ShutdownReport {
+    /// The IDs of the dropped cogs, in insertion order
+    pub dropped: Vec<CogId>,
+}
+
+/// How long to wait before retrying a panicked cog (task)
+///
+/// The wait happens on the engine running the cog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backoff {
+    /// Retry right away
+    #[default]
+    None,
+    /// Wait the same duration before every retry
+    Fixed(Duration),
+    /// Wait the given duration before the first retry, doubling it for every retry after that
+    Exponential(Duration),
+}
+
+impl Backoff {
+    /// How long to wait before the retry with index `retry`, counting from 0
+    ///
+    /// Exponential delays saturate at `Duration::MAX` instead of overflowing.
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::types::Backoff;
+    /// use std::time::Duration;
+    ///
+    /// let delays = |backoff: Backoff| (0..4).map(|retry| backoff.delay(retry)).collect::<Vec<_>>();
+    /// let ms = Duration::from_millis;
+    ///
+    /// assert_eq!(delays(Backoff::None), vec![Duration::ZERO; 4]);
+    /// assert_eq!(delays(Backoff::Fixed(ms(10))), vec![ms(10); 4]);
+    /// assert_eq!(
+    ///     delays(Backoff::Exponential(ms(10))),
+    ///     vec![ms(10), ms(20), ms(40), ms(80)]
+    /// );
+    ///
+    /// assert_eq!(Backoff::Exponential(ms(10)).delay(u32::MAX), Duration::MAX);
+    /// assert_eq!(Backoff::Exponential(Duration::MAX).delay(1), Duration::MAX);
+    /// ```
+    pub fn delay(self, retry: u32) -> Duration {
+        match self {
+            Backoff::None => Duration::ZERO,
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential(delay) => match 2u32.checked_pow(retry) {
+                Some(factor) => delay.saturating_mul(factor),
+                None if delay.is_zero() => Duration::ZERO,
+                None => Duration::MAX,
+            },
+        }
+    }
+}
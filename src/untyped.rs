@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+use crate::{
+    error::MachineError,
+    handle::CogHandle,
+    machine::Machine,
+    types::{AnyResult, CogType, MachineId, Priority, ShutdownMode, ShutdownReport},
+};
+
+/// A machine whose cogs can each return a different type
+///
+/// Results are stored type-erased, as `AnyResult`, and every cog gets a `CogHandle` typed to
+/// its own result, so one pool of engines can serve cogs of any type.
+///
+/// # Example
+/// ```
+/// use rustycog::AnyMachine;
+///
+/// let machine = AnyMachine::powered(4);
+/// let answer = machine.insert_cog(|| 42);
+/// let greeting = machine.insert_cog(|| String::from("Hello, RustyCog!"));
+///
+/// assert_eq!(answer.wait(), Ok(42));
+/// assert_eq!(greeting.wait().unwrap(), "Hello, RustyCog!");
+/// ```
+pub struct AnyMachine {
+    machine: Machine<AnyResult>,
+}
+
+impl AnyMachine {
+    /// Creates a new, powered untyped machine, see `Machine::powered`
+    pub fn powered(max_engines: u32) -> Self {
+        Self {
+            machine: Machine::powered(max_engines),
+        }
+    }
+
+    /// Creates a new, powered untyped machine that scales its engines with the workload,
+    /// see `Machine::dynamic`
+    pub fn dynamic(min_engines: u32, max_engines: u32, keep_alive: Duration) -> Self {
+        Self {
+            machine: Machine::dynamic(min_engines, max_engines, keep_alive),
+        }
+    }
+
+    /// Creates a new, cold untyped machine, see `Machine::cold`
+    pub fn cold(max_engines: u32) -> Self {
+        Self {
+            machine: Machine::cold(max_engines),
+        }
+    }
+
+    /// Power on a cold machine, see `Machine::power`
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The machine is already powered (`MachineError::AlreadyPowered`)
+    pub fn power(&self) -> Result<(), MachineError> {
+        self.machine.power()
+    }
+
+    /// Pause the machine, see `Machine::pause`
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The machine is already paused (`MachineError::AlreadyPaused`)
+    pub fn pause(&self) -> Result<(), MachineError> {
+        self.machine.pause()
+    }
+
+    /// Resume a paused machine, see `Machine::resume`
+    ///
+    /// # Errors
+    /// This function will return an error if:
+    /// - The machine is not paused (`MachineError::NotPaused`)
+    pub fn resume(&self) -> Result<(), MachineError> {
+        self.machine.resume()
+    }
+
+    /// Shut down the machine, see `Machine::shutdown`
+    pub fn shutdown(&self, mode: ShutdownMode) -> ShutdownReport {
+        self.machine.shutdown(mode)
+    }
+
+    /// The amount of engines currently running
+    pub fn engine_count(&self) -> usize {
+        self.machine.engine_count()
+    }
+
+    /// The identity of the machine, unique within the process
+    pub fn id(&self) -> MachineId {
+        self.machine.id()
+    }
+
+    /// Insert a cog into the machine, returning a handle typed to its result
+    ///
+    /// # Example
+    /// ```
+    /// use rustycog::AnyMachine;
+    ///
+    /// let machine = AnyMachine::powered(4);
+    /// let handle = machine.insert_cog(|| vec![1, 2, 3]);
+    ///
+    /// let numbers: Vec<i32> = handle.wait().unwrap();
+    /// assert_eq!(numbers.len(), 3);
+    /// ```
+    pub fn insert_cog<R, F>(&self, func: F) -> CogHandle<R>
+    where
+        R: CogType,
+        F: FnOnce() -> R + Send + std::panic::UnwindSafe + 'static,
+    {
+        self.machine
+            .insert_cog_with_handle(move || Box::new(func()) as AnyResult)
+            .downcast()
+    }
+
+    /// Insert a cog with a priority into the machine, see `Machine::insert_cog_with_priority`
+    pub fn insert_cog_with_priority<R, F>(&self, func: F, priority: Priority) -> CogHandle<R>
+    where
+        R: CogType,
+        F: FnOnce() -> R + Send + std::panic::UnwindSafe + 'static,
+    {
+        let id = self
+            .machine
+            .insert_cog_with_priority(move || Box::new(func()) as AnyResult, priority);
+        self.machine
+            .handle(id)
+            .expect("The cog was just inserted")
+            .downcast()
+    }
+
+    /// The underlying machine, for everything that works with cog IDs and `AnyResult`s
+    pub fn machine(&self) -> &Machine<AnyResult> {
+        &self.machine
+    }
+}
@@ -0,0 +1,119 @@
+use std::{
+    collections::VecDeque,
+    sync::{Condvar, Mutex},
+    time::Instant,
+};
+
+use crate::types::CogId;
+
+/// Collects the IDs of cogs as they finish, so several cogs can be waited on at once
+/// through a single condvar
+#[derive(Default)]
+pub struct Watch {
+    finished: Mutex<Finished>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct Finished {
+    count: usize,
+    ids: VecDeque<CogId>,
+}
+
+impl Watch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called by a cog once it has finished
+    pub fn notify(&self, id: CogId) {
+        let mut finished = self.finished.lock().unwrap();
+        finished.count += 1;
+        finished.ids.push_back(id);
+        self.changed.notify_all();
+    }
+
+    /// Blocks until at least `count` of the watched cogs have finished
+    pub fn wait_for(&self, count: usize) {
+        let mut finished = self.finished.lock().unwrap();
+        while finished.count < count {
+            finished = self.changed.wait(finished).unwrap();
+        }
+    }
+
+    /// Blocks until a watched cog finishes, returning the cogs in the order they finished
+    pub fn next(&self) -> CogId {
+        self.next_before(None)
+            .expect("Without a deadline, the wait can only end with a finished cog")
+    }
+
+    /// Like `Watch::next`, but gives up once `deadline` has passed
+    pub fn next_before(&self, deadline: Option<Instant>) -> Option<CogId> {
+        let mut finished = self.finished.lock().unwrap();
+        loop {
+            if let Some(id) = finished.ids.pop_front() {
+                return Some(id);
+            }
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return None;
+                    }
+                    finished = self
+                        .changed
+                        .wait_timeout(finished, deadline - now)
+                        .unwrap()
+                        .0;
+                }
+                None => finished = self.changed.wait(finished).unwrap(),
+            }
+        }
+    }
+}
+
+/// Counts the cogs of a machine that have not finished yet
+#[derive(Default)]
+pub struct Outstanding {
+    count: Mutex<usize>,
+    idle: Condvar,
+}
+
+impl Outstanding {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Called for every cog inserted into the machine
+    pub fn add(&self, amount: usize) {
+        *self.count.lock().unwrap() += amount;
+    }
+
+    /// Called by a cog once it has finished
+    pub fn finish(&self) {
+        let mut count = self.count.lock().unwrap();
+        *count -= 1;
+        if *count == 0 {
+            self.idle.notify_all();
+        }
+    }
+
+    /// Blocks until no cog is left unfinished, or until `deadline` has passed.
+    /// Returns true if every cog has finished.
+    pub fn wait(&self, deadline: Option<Instant>) -> bool {
+        let mut count = self.count.lock().unwrap();
+        while *count > 0 {
+            match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return false;
+                    }
+                    count = self.idle.wait_timeout(count, deadline - now).unwrap().0;
+                }
+                None => count = self.idle.wait(count).unwrap(),
+            }
+        }
+        true
+    }
+}
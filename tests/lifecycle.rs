@@ -0,0 +1,128 @@
+use std::{
+    sync::{Arc, mpsc::channel},
+    time::Duration,
+};
+
+use rustycog::{Machine, error::CogError, types::ShutdownMode};
+
+#[test]
+fn power_runs_cogs_inserted_while_cold() {
+    let machine = Machine::cold(2);
+    let parse_id = machine.insert_cog(|| 20);
+    let double_id = machine
+        .insert_cog_then(parse_id, |parsed| parsed * 2)
+        .unwrap();
+
+    machine.power().unwrap();
+    assert_eq!(
+        machine.wait_for_result_timeout(double_id, Duration::from_secs(10)),
+        Ok(40)
+    );
+}
+
+#[test]
+fn paused_cold_machine_holds_off_waiting_threads() {
+    let machine = Machine::cold(1);
+    machine.pause().unwrap();
+
+    let first_id = machine.insert_cog(|| 1);
+    let second_id = machine.insert_cog_after(&[first_id], || 2).unwrap();
+    let handle = machine.handle(second_id).unwrap();
+    let waiter = std::thread::spawn(move || handle.wait());
+
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(
+        machine.get_result(first_id),
+        Err(CogError::NotCompleted(first_id))
+    );
+
+    machine.resume().unwrap();
+    assert_eq!(waiter.join().unwrap(), Ok(2));
+}
+
+#[test]
+fn abort_keeps_paused_machine_paused() {
+    let machine = Machine::<i32>::powered(2);
+    machine.pause().unwrap();
+
+    machine.shutdown(ShutdownMode::Abort);
+    assert!(machine.is_paused());
+
+    machine.shutdown(ShutdownMode::Drain);
+    assert!(!machine.is_paused());
+}
+
+#[test]
+fn drain_runs_cogs_inserted_while_cold_after_power() {
+    let machine = Machine::powered(2);
+    machine.shutdown(ShutdownMode::Drain);
+
+    let id = machine.insert_cog(|| 8);
+    machine.power().unwrap();
+    assert!(machine.shutdown(ShutdownMode::Drain).dropped.is_empty());
+    assert_eq!(machine.get_result(id), Ok(8));
+}
+
+#[test]
+fn machine_dropped_by_its_own_cog() {
+    let machine = Arc::new(Machine::powered(2));
+    let (go_tx, go_rx) = channel();
+    let (done_tx, done_rx) = channel();
+
+    let shared = machine.clone();
+    machine.insert_cog(move || {
+        go_rx.recv().unwrap();
+        drop(shared);
+        done_tx.send(()).unwrap();
+        0
+    });
+
+    drop(machine);
+    go_tx.send(()).unwrap();
+    done_rx.recv().unwrap();
+}
+
+#[test]
+fn machine_dropped_by_cancelling_its_last_cog() {
+    let machine = Arc::new(Machine::cold(1));
+    let shared = machine.clone();
+    let handle = machine.insert_cog_with_handle(move || {
+        let _ = &shared;
+        0
+    });
+    let id = handle.id();
+
+    drop(machine);
+    assert_eq!(handle.cancel(), Ok(()));
+    assert_eq!(handle.wait(), Err(CogError::Cancelled(id)));
+}
+
+#[test]
+fn shutdown_reaches_consumed_cogs() {
+    let machine = Machine::cold(2);
+    let parse_id = machine.insert_cog(|| 20);
+    let double_id = machine
+        .insert_cog_then(parse_id, |parsed| parsed * 2)
+        .unwrap();
+
+    assert!(machine.shutdown(ShutdownMode::Drain).dropped.is_empty());
+    assert_eq!(machine.get_result(double_id), Ok(40));
+
+    let parse_id = machine.insert_cog(|| 20);
+    let double_id = machine
+        .insert_cog_then(parse_id, |parsed| parsed * 2)
+        .unwrap();
+
+    let report = machine.shutdown(ShutdownMode::Abort);
+    assert_eq!(report.dropped, vec![parse_id, double_id]);
+    assert_eq!(
+        machine.get_result(double_id),
+        Err(CogError::Shutdown(double_id))
+    );
+
+    machine.power().unwrap();
+    assert_eq!(
+        machine.wait_until_done_timeout(Duration::from_secs(10)),
+        Ok(())
+    );
+}
@@ -0,0 +1,59 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::Duration,
+};
+
+use rustycog::{Machine, types::ShutdownMode};
+
+/// Reads the borrowed flag as late as possible while it is being dropped
+struct SetOnDrop<'a>(&'a AtomicBool);
+
+impl Drop for SetOnDrop<'_> {
+    fn drop(&mut self) {
+        thread::sleep(Duration::from_millis(50));
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn scoped_cog_cancelled_from_another_thread() {
+    let machine = Machine::powered(1);
+    machine.pause().unwrap();
+    let dropped = AtomicBool::new(false);
+
+    let canceller = machine.scope(|scope| {
+        let guard = SetOnDrop(&dropped);
+        let handle = scope.insert_cog(move || {
+            let _ = &guard;
+            0
+        });
+        thread::spawn(move || handle.cancel())
+    });
+
+    assert!(dropped.load(Ordering::SeqCst));
+    assert_eq!(canceller.join().unwrap(), Ok(()));
+}
+
+#[test]
+fn scoped_cog_aborted_from_another_thread() {
+    let machine = Arc::new(Machine::powered(1));
+    machine.pause().unwrap();
+    let dropped = AtomicBool::new(false);
+
+    let aborter = machine.scope(|scope| {
+        let guard = SetOnDrop(&dropped);
+        let handle = scope.insert_cog(move || {
+            let _ = &guard;
+            0
+        });
+        let machine = machine.clone();
+        thread::spawn(move || machine.shutdown(ShutdownMode::Abort).dropped == [handle.id()])
+    });
+
+    assert!(dropped.load(Ordering::SeqCst));
+    assert!(aborter.join().unwrap());
+}